@@ -1,6 +1,24 @@
+pub mod stream;
+
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use stream::{NdjsonParser, SseParser};
+
+const CONNECT_TIMEOUT_SECS: u64 = 10;
+
+/// Longest wait for response headers or the next part of the body. A long
+/// streamed reply is fine as long as it keeps arriving.
+const IDLE_TIMEOUT_SECS: u64 = 120;
+
+const OPENAI_BASE_URL: &str = "https://api.openai.com/v1";
+const ANTHROPIC_BASE_URL: &str = "https://api.anthropic.com/v1";
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -8,7 +26,7 @@ pub struct ChatMessage {
     pub content: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum MessageRole {
     System,
@@ -16,19 +34,259 @@ pub enum MessageRole {
     Assistant,
 }
 
-#[derive(Debug, Clone)]
+/// Why the model stopped generating.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StopReason {
+    /// The model finished its answer.
+    EndTurn,
+    /// Cut off by the token limit; the content is incomplete.
+    MaxTokens,
+    StopSequence,
+    ToolUse,
+    /// Withheld or cut short by the provider's safety filters.
+    ContentFilter,
+    /// A reason this version does not know, as the provider sent it.
+    Other(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Usage {
+    pub input_tokens: u32,
+    pub output_tokens: u32,
+}
+
+/// A reply with the details `chat` leaves out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChatCompletion {
+    pub content: String,
+    pub stop_reason: Option<StopReason>,
+    pub usage: Option<Usage>,
+}
+
+/// Sent while a reply streams in: any number of `Delta`s, then exactly one
+/// `Done` or `Error`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    Delta {
+        text: String,
+    },
+    Done {
+        stop_reason: Option<StopReason>,
+        usage: Option<Usage>,
+    },
+    Error {
+        message: String,
+        /// HTTP status and error type when the provider API returned the error.
+        status: Option<u16>,
+        kind: Option<String>,
+    },
+}
+
+impl StreamEvent {
+    fn from_error(error: &anyhow::Error) -> Self {
+        let api_error = error.downcast_ref::<ApiError>();
+        StreamEvent::Error {
+            message: error.to_string(),
+            status: api_error.map(|e| e.status),
+            kind: api_error.and_then(|e| e.kind.clone()),
+        }
+    }
+}
+
+/// Sends a text delta, failing once the receiver has gone away so the
+/// provider stops reading the response.
+async fn send_delta(events: &mpsc::Sender<StreamEvent>, text: &str) -> Result<()> {
+    if text.is_empty() {
+        return Ok(());
+    }
+    events
+        .send(StreamEvent::Delta { text: text.to_string() })
+        .await
+        .map_err(|_| anyhow::anyhow!("Stream receiver closed"))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum AIProvider {
     OpenAI,
+    #[serde(rename = "openai_compatible")]
+    OpenAICompatible,
     Anthropic,
+    Ollama,
     Local,
 }
 
+/// Provider settings as sent by the frontend. Unset fields keep the
+/// provider's defaults. API keys from the environment are only used with the
+/// provider's default base URL.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConfig {
+    pub provider: AIProvider,
+    /// Name an `openai_compatible` provider is registered under.
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub max_tokens: Option<u32>,
+    /// Extra request headers, for `openai_compatible` providers.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// How long an `openai_compatible` reply may stall before it fails.
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// How long an `ollama` model stays loaded after a reply, see
+    /// `OllamaProvider::with_keep_alive_secs`.
+    #[serde(default)]
+    pub keep_alive_secs: Option<i64>,
+}
+
+impl ProviderConfig {
+    /// Fails when required settings are missing, such as the base URL of an
+    /// `openai_compatible` provider.
+    pub fn build(&self) -> Result<Box<dyn AIProviderTrait>> {
+        Ok(match self.provider {
+            AIProvider::OpenAI => {
+                let mut provider = OpenAIProvider::new();
+                if let Some(api_key) = self.api_key.clone().filter(|k| !k.is_empty()) {
+                    provider = provider.with_api_key(api_key);
+                }
+                if let Some(base_url) = &self.base_url {
+                    provider = provider.with_base_url(base_url.as_str());
+                }
+                if let Some(model) = &self.model {
+                    provider = provider.with_model(model.as_str());
+                }
+                if let Some(temperature) = self.temperature {
+                    provider = provider.with_temperature(temperature);
+                }
+                if let Some(max_tokens) = self.max_tokens {
+                    provider = provider.with_max_tokens(max_tokens);
+                }
+                Box::new(provider)
+            }
+            AIProvider::OpenAICompatible => {
+                let name = self
+                    .name
+                    .clone()
+                    .filter(|n| !n.is_empty())
+                    .unwrap_or_else(|| "openai_compatible".to_string());
+                let base_url = self
+                    .base_url
+                    .as_deref()
+                    .map(str::trim)
+                    .filter(|url| !url.is_empty())
+                    .ok_or_else(|| anyhow::anyhow!("Provider {} has no base URL", name))?;
+                let mut provider = OpenAICompatibleProvider::new(name, base_url);
+                if let Some(api_key) = self.api_key.clone().filter(|k| !k.is_empty()) {
+                    provider = provider.with_api_key(api_key);
+                }
+                for (name, value) in &self.headers {
+                    provider = provider.with_header(name.as_str(), value.as_str());
+                }
+                if let Some(model) = &self.model {
+                    provider = provider.with_model(model.as_str());
+                }
+                if let Some(temperature) = self.temperature {
+                    provider = provider.with_temperature(temperature);
+                }
+                if let Some(max_tokens) = self.max_tokens {
+                    provider = provider.with_max_tokens(max_tokens);
+                }
+                if let Some(timeout_secs) = self.timeout_secs {
+                    provider = provider.with_timeout(Duration::from_secs(timeout_secs));
+                }
+                Box::new(provider)
+            }
+            AIProvider::Anthropic => {
+                let mut provider = AnthropicProvider::new();
+                if let Some(api_key) = self.api_key.clone().filter(|k| !k.is_empty()) {
+                    provider = provider.with_api_key(api_key);
+                }
+                if let Some(base_url) = &self.base_url {
+                    provider = provider.with_base_url(base_url.as_str());
+                }
+                if let Some(model) = &self.model {
+                    provider = provider.with_model(model.as_str());
+                }
+                if let Some(temperature) = self.temperature {
+                    provider = provider.with_temperature(temperature);
+                }
+                if let Some(max_tokens) = self.max_tokens {
+                    provider = provider.with_max_tokens(max_tokens);
+                }
+                Box::new(provider)
+            }
+            AIProvider::Ollama => {
+                let mut provider = OllamaProvider::new();
+                if let Some(base_url) = &self.base_url {
+                    provider = provider.with_base_url(base_url.as_str());
+                }
+                if let Some(model) = &self.model {
+                    provider = provider.with_model(model.as_str());
+                }
+                if let Some(temperature) = self.temperature {
+                    provider = provider.with_temperature(temperature);
+                }
+                if let Some(max_tokens) = self.max_tokens {
+                    provider = provider.with_max_tokens(max_tokens);
+                }
+                if let Some(keep_alive_secs) = self.keep_alive_secs {
+                    provider = provider.with_keep_alive_secs(keep_alive_secs);
+                }
+                Box::new(provider)
+            }
+            AIProvider::Local => Box::new(LocalProvider::new()),
+        })
+    }
+}
+
 #[async_trait::async_trait]
 pub trait AIProviderTrait: Send + Sync {
     fn name(&self) -> &str;
     async fn chat(&self, messages: Vec<ChatMessage>) -> Result<String>;
-    // Note: stream_chat removed for now as Iterator is not compatible with async traits
-    // Will be implemented using channels or async streams in the future
+
+    /// Like `chat`, with the stop reason and token usage when the provider
+    /// reports them.
+    async fn complete(&self, messages: Vec<ChatMessage>) -> Result<ChatCompletion> {
+        Ok(ChatCompletion {
+            content: self.chat(messages).await?,
+            stop_reason: None,
+            usage: None,
+        })
+    }
+
+    /// Sends the reply as `Delta` events while it is generated and returns
+    /// it whole. Providers that cannot stream send it as a single delta.
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        events: &mpsc::Sender<StreamEvent>,
+    ) -> Result<ChatCompletion> {
+        let completion = self.complete(messages).await?;
+        send_delta(events, &completion.content).await?;
+        Ok(completion)
+    }
+
+    /// Streams the reply to `events`, ending with `Done` or `Error`.
+    async fn stream_chat(&self, messages: Vec<ChatMessage>, events: mpsc::Sender<StreamEvent>) {
+        let last = match self.stream_completion(messages, &events).await {
+            Ok(completion) => StreamEvent::Done {
+                stop_reason: completion.stop_reason,
+                usage: completion.usage,
+            },
+            Err(e) => StreamEvent::from_error(&e),
+        };
+        let _ = events.send(last).await;
+    }
 }
 
 pub struct AIService {
@@ -43,6 +301,7 @@ impl AIService {
         // Add default providers
         providers.insert("openai".to_string(), Box::new(OpenAIProvider::new()));
         providers.insert("anthropic".to_string(), Box::new(AnthropicProvider::new()));
+        providers.insert("ollama".to_string(), Box::new(OllamaProvider::new()));
         providers.insert("local".to_string(), Box::new(LocalProvider::new()));
 
         Self {
@@ -59,6 +318,32 @@ impl AIService {
         provider.chat(messages).await
     }
 
+    pub async fn complete(&self, messages: Vec<ChatMessage>, provider: Option<&str>) -> Result<ChatCompletion> {
+        let provider_name = provider.unwrap_or(&self.default_provider);
+        let provider = self.providers.get(provider_name)
+            .ok_or_else(|| anyhow::anyhow!("Provider {} not found", provider_name))?;
+
+        provider.complete(messages).await
+    }
+
+    /// Streams the reply to `events`; see `AIProviderTrait::stream_chat`.
+    pub async fn stream_chat(&self, messages: Vec<ChatMessage>, provider: Option<&str>, events: mpsc::Sender<StreamEvent>) {
+        let provider_name = provider.unwrap_or(&self.default_provider);
+        match self.providers.get(provider_name) {
+            Some(provider) => provider.stream_chat(messages, events).await,
+            None => {
+                let error = anyhow::anyhow!("Provider {} not found", provider_name);
+                let _ = events.send(StreamEvent::from_error(&error)).await;
+            }
+        }
+    }
+
+    /// Adds `provider` under its `name()`, replacing any provider already
+    /// registered under that name.
+    pub fn register(&mut self, provider: Box<dyn AIProviderTrait>) {
+        self.providers.insert(provider.name().to_string(), provider);
+    }
+
     pub fn set_default_provider(&mut self, provider: String) {
         if self.providers.contains_key(&provider) {
             self.default_provider = provider;
@@ -66,51 +351,601 @@ impl AIService {
     }
 }
 
+/// A provider API answered with an error status. Returned inside
+/// `anyhow::Error`, so callers can `downcast_ref` it to tell e.g. a bad key
+/// (401) from rate limiting (429).
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub provider: String,
+    pub status: u16,
+    /// Error type from the body, e.g. `invalid_request_error`.
+    pub kind: Option<String>,
+    pub message: String,
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} API error ({}): {}", self.provider, self.status, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl ApiError {
+    /// Reads `{"error": {"message", "type"}}`, or `{"error": "..."}`, and
+    /// falls back to the raw body for anything else, e.g. a proxy's HTML page.
+    fn from_body(provider: &str, status: u16, body: &str) -> Self {
+        let error = serde_json::from_str::<Value>(body).ok().and_then(|v| v.get("error").cloned());
+        let (kind, message) = match &error {
+            Some(Value::Object(error)) => (
+                error.get("type").and_then(Value::as_str).map(str::to_string),
+                error.get("message").and_then(Value::as_str).map(str::to_string),
+            ),
+            Some(Value::String(message)) => (None, Some(message.clone())),
+            _ => (None, None),
+        };
+
+        Self {
+            provider: provider.to_string(),
+            status,
+            kind,
+            message: message.unwrap_or_else(|| {
+                let body = body.trim();
+                if body.is_empty() {
+                    format!("HTTP {}", status)
+                } else {
+                    body.chars().take(500).collect()
+                }
+            }),
+        }
+    }
+}
+
+/// Waits at most `timeout` for the next step of a response.
+async fn within<T>(timeout: Duration, step: impl Future<Output = reqwest::Result<T>>) -> Result<T> {
+    match tokio::time::timeout(timeout, step).await {
+        Ok(result) => Ok(result?),
+        Err(_) => Err(anyhow::anyhow!("No response from the server for {} seconds", timeout.as_secs())),
+    }
+}
+
+/// Sends `request`, turning an error status into the `ApiError` in its body.
+async fn send(provider: &str, request: reqwest::RequestBuilder, timeout: Duration) -> Result<reqwest::Response> {
+    let response = within(timeout, request.send()).await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = read_body(response, timeout).await?;
+        return Err(ApiError::from_body(provider, status.as_u16(), &body).into());
+    }
+    Ok(response)
+}
+
+/// Reads the whole body, allowing `timeout` between chunks.
+async fn read_body(mut response: reqwest::Response, timeout: Duration) -> Result<String> {
+    let mut body = Vec::new();
+    while let Some(chunk) = within(timeout, response.chunk()).await? {
+        body.extend_from_slice(&chunk);
+    }
+    Ok(String::from_utf8_lossy(&body).into_owned())
+}
+
+/// Error events inside a stream have the same shape as error bodies. The
+/// response status was already 200 by then, so that is the status reported.
+fn stream_error(provider: &str, data: &str) -> Option<anyhow::Error> {
+    let value: Value = serde_json::from_str(data).ok()?;
+    value.get("error")?;
+    Some(ApiError::from_body(provider, 200, data).into())
+}
+
+/// Only connecting is limited here. Reading the response is limited per
+/// step with `within`, as a total limit would cut off long streamed replies.
+fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+        .build()
+        .unwrap_or_default()
+}
+
 // OpenAI Provider
 pub struct OpenAIProvider {
+    name: String,
     api_key: Option<String>,
+    /// `OPENAI_API_KEY`, only sent to the default base URL.
+    env_api_key: Option<String>,
+    /// False for compatible servers, which often run without keys.
+    api_key_required: bool,
     base_url: String,
+    headers: Vec<(String, String)>,
+    model: String,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+    idle_timeout: Duration,
+    client: reqwest::Client,
+}
+
+#[derive(Serialize)]
+struct OpenAIRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+    /// Asks for a final chunk with token usage when streaming.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_options: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIResponse {
+    choices: Vec<OpenAIChoice>,
+    usage: Option<OpenAIUsage>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIChoice {
+    message: OpenAIResponseMessage,
+    finish_reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIUsage {
+    prompt_tokens: u32,
+    completion_tokens: u32,
+}
+
+impl From<OpenAIUsage> for Usage {
+    fn from(usage: OpenAIUsage) -> Self {
+        Usage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct OpenAIStreamChunk {
+    #[serde(default)]
+    choices: Vec<OpenAIStreamChoice>,
+    usage: Option<OpenAIUsage>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIStreamChoice {
+    #[serde(default)]
+    delta: OpenAIResponseMessage,
+    finish_reason: Option<String>,
+}
+
+fn openai_stop_reason(reason: String) -> StopReason {
+    match reason.as_str() {
+        "stop" => StopReason::EndTurn,
+        "length" => StopReason::MaxTokens,
+        "tool_calls" | "function_call" => StopReason::ToolUse,
+        "content_filter" => StopReason::ContentFilter,
+        _ => StopReason::Other(reason),
+    }
+}
+
+#[derive(Default, Deserialize)]
+struct OpenAIResponseMessage {
+    content: Option<String>,
 }
 
 impl OpenAIProvider {
     pub fn new() -> Self {
         Self {
-            api_key: std::env::var("OPENAI_API_KEY").ok(),
-            base_url: "https://api.openai.com/v1".to_string(),
+            name: "openai".to_string(),
+            api_key: None,
+            env_api_key: std::env::var("OPENAI_API_KEY").ok().filter(|k| !k.is_empty()),
+            api_key_required: true,
+            base_url: OPENAI_BASE_URL.to_string(),
+            headers: Vec::new(),
+            model: "gpt-4o-mini".to_string(),
+            temperature: None,
+            max_tokens: None,
+            idle_timeout: Duration::from_secs(IDLE_TIMEOUT_SECS),
+            client: http_client(),
         }
     }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// API root without the `/chat/completions` suffix. `OPENAI_API_KEY`
+    /// is not sent to other URLs, so they need `with_api_key`.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// The key set with `with_api_key`, or the environment's while the base
+    /// URL is the default, so it never goes to another host.
+    fn api_key(&self) -> Option<&str> {
+        self.api_key.as_deref().or_else(|| {
+            let default_url = self.base_url.trim_end_matches('/') == OPENAI_BASE_URL;
+            self.env_api_key.as_deref().filter(|_| default_url)
+        })
+    }
+
+    fn request(&self, messages: &[ChatMessage], stream: bool) -> Result<reqwest::RequestBuilder> {
+        let api_key = self.api_key();
+        if self.api_key_required && api_key.is_none() {
+            return Err(anyhow::anyhow!("OpenAI API key not set"));
+        }
+
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let body = OpenAIRequest {
+            model: &self.model,
+            messages,
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+            stream,
+            stream_options: stream.then(|| serde_json::json!({ "include_usage": true })),
+        };
+
+        let mut request = self.client.post(&url).json(&body);
+        if let Some(api_key) = api_key {
+            request = request.bearer_auth(api_key);
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        Ok(request)
+    }
 }
 
 #[async_trait::async_trait]
 impl AIProviderTrait for OpenAIProvider {
     fn name(&self) -> &str {
-        "openai"
+        &self.name
     }
 
     async fn chat(&self, messages: Vec<ChatMessage>) -> Result<String> {
-        if self.api_key.is_none() {
-            return Err(anyhow::anyhow!("OpenAI API key not set"));
+        Ok(self.complete(messages).await?.content)
+    }
+
+    async fn complete(&self, messages: Vec<ChatMessage>) -> Result<ChatCompletion> {
+        let response = send(self.name(), self.request(&messages, false)?, self.idle_timeout).await?;
+        let body = read_body(response, self.idle_timeout).await?;
+
+        let response: OpenAIResponse = serde_json::from_str(&body)
+            .map_err(|e| anyhow::anyhow!("Invalid OpenAI response: {}", e))?;
+        let choice = response
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("OpenAI response has no choices"))?;
+        let stop_reason = choice.finish_reason.map(openai_stop_reason);
+        let content = match choice.message.content {
+            Some(content) => content,
+            // A filtered reply may come back without content
+            None if stop_reason.is_some() => String::new(),
+            None => return Err(anyhow::anyhow!("OpenAI response has no message content")),
+        };
+
+        Ok(ChatCompletion {
+            content,
+            stop_reason,
+            usage: response.usage.map(Usage::from),
+        })
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        events: &mpsc::Sender<StreamEvent>,
+    ) -> Result<ChatCompletion> {
+        let mut response = send(self.name(), self.request(&messages, true)?, self.idle_timeout).await?;
+        let mut parser = SseParser::new();
+        let mut completion = ChatCompletion::default();
+
+        loop {
+            let chunk = within(self.idle_timeout, response.chunk()).await?;
+            let sse_events = match &chunk {
+                Some(chunk) => parser.push(chunk),
+                None => parser.finish().into_iter().collect(),
+            };
+
+            for event in sse_events {
+                if event.data == "[DONE]" {
+                    return Ok(completion);
+                }
+                if let Some(error) = stream_error(self.name(), &event.data) {
+                    return Err(error);
+                }
+
+                let data: OpenAIStreamChunk = serde_json::from_str(&event.data)
+                    .map_err(|e| anyhow::anyhow!("Invalid OpenAI stream chunk: {}", e))?;
+                if let Some(usage) = data.usage {
+                    completion.usage = Some(usage.into());
+                }
+                for choice in data.choices {
+                    if let Some(text) = choice.delta.content {
+                        send_delta(events, &text).await?;
+                        completion.content.push_str(&text);
+                    }
+                    if let Some(reason) = choice.finish_reason {
+                        completion.stop_reason = Some(openai_stop_reason(reason));
+                    }
+                }
+            }
+
+            if chunk.is_none() {
+                break;
+            }
         }
 
-        // TODO: Implement actual OpenAI API call
-        Ok("OpenAI provider not yet implemented".to_string())
+        // Some compatible servers close the stream without `[DONE]`
+        if completion.stop_reason.is_none() {
+            return Err(anyhow::anyhow!("OpenAI stream ended before the reply was complete"));
+        }
+        Ok(completion)
+    }
+
+}
+
+// OpenAI-compatible Provider
+
+/// A server that speaks the OpenAI Chat Completions protocol at another
+/// URL, such as vLLM, LM Studio or LiteLLM. Each instance has its own name,
+/// so several can be registered with `AIService::register`.
+pub struct OpenAICompatibleProvider {
+    inner: OpenAIProvider,
+}
+
+impl OpenAICompatibleProvider {
+    /// `base_url` is the API root, usually ending in `/v1`. No API key is
+    /// sent unless one is set.
+    pub fn new(name: impl Into<String>, base_url: impl Into<String>) -> Self {
+        let mut inner = OpenAIProvider::new();
+        inner.name = name.into();
+        inner.env_api_key = None;
+        inner.api_key_required = false;
+        inner.base_url = base_url.into();
+
+        Self { inner }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.inner = self.inner.with_api_key(api_key);
+        self
+    }
+
+    /// Sent with every request, e.g. a gateway's routing or tenant header.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.inner.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Model used when the caller does not pick one.
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.inner = self.inner.with_model(model);
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.inner = self.inner.with_temperature(temperature);
+        self
     }
 
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.inner = self.inner.with_max_tokens(max_tokens);
+        self
+    }
+
+    /// Longest wait for the reply to start or continue, not a limit on the
+    /// whole reply. Local servers loading a large model may need more than
+    /// the default.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.inner.idle_timeout = timeout;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl AIProviderTrait for OpenAICompatibleProvider {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    async fn chat(&self, messages: Vec<ChatMessage>) -> Result<String> {
+        self.inner.chat(messages).await
+    }
+
+    async fn complete(&self, messages: Vec<ChatMessage>) -> Result<ChatCompletion> {
+        self.inner.complete(messages).await
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        events: &mpsc::Sender<StreamEvent>,
+    ) -> Result<ChatCompletion> {
+        self.inner.stream_completion(messages, events).await
+    }
 }
 
 // Anthropic Provider
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// The Messages API requires `max_tokens`.
+const ANTHROPIC_DEFAULT_MAX_TOKENS: u32 = 4096;
+
 pub struct AnthropicProvider {
     api_key: Option<String>,
+    /// `ANTHROPIC_API_KEY`, only sent to the default base URL.
+    env_api_key: Option<String>,
     base_url: String,
+    model: String,
+    temperature: Option<f32>,
+    max_tokens: u32,
+    idle_timeout: Duration,
+    client: reqwest::Client,
+}
+
+#[derive(Serialize)]
+struct AnthropicRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<String>,
+    messages: Vec<AnthropicMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Serialize)]
+struct AnthropicMessage {
+    role: MessageRole,
+    content: String,
+}
+
+#[derive(Deserialize)]
+struct AnthropicResponse {
+    content: Vec<AnthropicContentBlock>,
+    stop_reason: Option<String>,
+    usage: Option<Usage>,
+}
+
+#[derive(Deserialize)]
+struct AnthropicContentBlock {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    text: String,
 }
 
 impl AnthropicProvider {
     pub fn new() -> Self {
         Self {
-            api_key: std::env::var("ANTHROPIC_API_KEY").ok(),
-            base_url: "https://api.anthropic.com/v1".to_string(),
+            api_key: None,
+            env_api_key: std::env::var("ANTHROPIC_API_KEY").ok().filter(|k| !k.is_empty()),
+            base_url: ANTHROPIC_BASE_URL.to_string(),
+            model: "claude-3-5-sonnet-latest".to_string(),
+            temperature: None,
+            max_tokens: ANTHROPIC_DEFAULT_MAX_TOKENS,
+            idle_timeout: Duration::from_secs(IDLE_TIMEOUT_SECS),
+            client: http_client(),
+        }
+    }
+
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// API root without the `/messages` suffix.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Like `OpenAIProvider::api_key`.
+    fn api_key(&self) -> Option<&str> {
+        self.api_key.as_deref().or_else(|| {
+            let default_url = self.base_url.trim_end_matches('/') == ANTHROPIC_BASE_URL;
+            self.env_api_key.as_deref().filter(|_| default_url)
+        })
+    }
+
+    fn request(&self, messages: Vec<ChatMessage>, stream: bool) -> Result<reqwest::RequestBuilder> {
+        let api_key = self
+            .api_key()
+            .ok_or_else(|| anyhow::anyhow!("Anthropic API key not set"))?;
+
+        let (system, messages) = anthropic_messages(messages);
+        let url = format!("{}/messages", self.base_url.trim_end_matches('/'));
+        let body = AnthropicRequest {
+            model: &self.model,
+            max_tokens: self.max_tokens,
+            system,
+            messages,
+            temperature: self.temperature,
+            stream,
+        };
+
+        Ok(self
+            .client
+            .post(&url)
+            .header("x-api-key", api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body))
+    }
+}
+
+fn anthropic_stop_reason(reason: String) -> StopReason {
+    match reason.as_str() {
+        "end_turn" => StopReason::EndTurn,
+        "max_tokens" => StopReason::MaxTokens,
+        "stop_sequence" => StopReason::StopSequence,
+        "tool_use" => StopReason::ToolUse,
+        "refusal" => StopReason::ContentFilter,
+        _ => StopReason::Other(reason),
+    }
+}
+
+/// Splits out the system prompt, which the Messages API takes as a
+/// top-level field, and merges consecutive turns of the same role, which it
+/// rejects.
+fn anthropic_messages(messages: Vec<ChatMessage>) -> (Option<String>, Vec<AnthropicMessage>) {
+    let mut system: Vec<String> = Vec::new();
+    let mut turns: Vec<AnthropicMessage> = Vec::new();
+
+    for message in messages {
+        match message.role {
+            MessageRole::System => system.push(message.content),
+            role => match turns.last_mut() {
+                Some(last) if last.role == role => {
+                    last.content.push_str("\n\n");
+                    last.content.push_str(&message.content);
+                }
+                _ => turns.push(AnthropicMessage {
+                    role,
+                    content: message.content,
+                }),
+            },
         }
     }
+
+    let system = if system.is_empty() { None } else { Some(system.join("\n\n")) };
+    (system, turns)
 }
 
 #[async_trait::async_trait]
@@ -120,16 +955,346 @@ impl AIProviderTrait for AnthropicProvider {
     }
 
     async fn chat(&self, messages: Vec<ChatMessage>) -> Result<String> {
-        if self.api_key.is_none() {
-            return Err(anyhow::anyhow!("Anthropic API key not set"));
+        Ok(self.complete(messages).await?.content)
+    }
+
+    async fn complete(&self, messages: Vec<ChatMessage>) -> Result<ChatCompletion> {
+        let response = send(self.name(), self.request(messages, false)?, self.idle_timeout).await?;
+        let body = read_body(response, self.idle_timeout).await?;
+
+        let response: AnthropicResponse = serde_json::from_str(&body)
+            .map_err(|e| anyhow::anyhow!("Invalid Anthropic response: {}", e))?;
+        let content = response
+            .content
+            .into_iter()
+            .filter(|block| block.kind == "text")
+            .map(|block| block.text)
+            .collect::<Vec<_>>()
+            .join("");
+
+        Ok(ChatCompletion {
+            content,
+            stop_reason: response.stop_reason.map(anthropic_stop_reason),
+            usage: response.usage,
+        })
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        events: &mpsc::Sender<StreamEvent>,
+    ) -> Result<ChatCompletion> {
+        let mut response = send(self.name(), self.request(messages, true)?, self.idle_timeout).await?;
+        let mut parser = SseParser::new();
+        let mut completion = ChatCompletion::default();
+
+        loop {
+            let chunk = within(self.idle_timeout, response.chunk()).await?;
+            let sse_events = match &chunk {
+                Some(chunk) => parser.push(chunk),
+                None => parser.finish().into_iter().collect(),
+            };
+
+            for event in sse_events {
+                let data: Value = serde_json::from_str(&event.data)
+                    .map_err(|e| anyhow::anyhow!("Invalid Anthropic stream event: {}", e))?;
+                let usage = completion.usage.get_or_insert_with(Usage::default);
+
+                match data.get("type").and_then(Value::as_str).unwrap_or_default() {
+                    "message_start" => {
+                        if let Some(tokens) = data.pointer("/message/usage/input_tokens").and_then(Value::as_u64) {
+                            usage.input_tokens = tokens as u32;
+                        }
+                    }
+                    "content_block_delta" => {
+                        if let Some(text) = data.pointer("/delta/text").and_then(Value::as_str) {
+                            send_delta(events, text).await?;
+                            completion.content.push_str(text);
+                        }
+                    }
+                    "message_delta" => {
+                        if let Some(tokens) = data.pointer("/usage/output_tokens").and_then(Value::as_u64) {
+                            usage.output_tokens = tokens as u32;
+                        }
+                        if let Some(reason) = data.pointer("/delta/stop_reason").and_then(Value::as_str) {
+                            completion.stop_reason = Some(anthropic_stop_reason(reason.to_string()));
+                        }
+                    }
+                    "message_stop" => return Ok(completion),
+                    "error" => {
+                        return Err(stream_error(self.name(), &event.data)
+                            .unwrap_or_else(|| anyhow::anyhow!("Anthropic stream error")))
+                    }
+                    // ping, content_block_start/stop
+                    _ => {}
+                }
+            }
+
+            if chunk.is_none() {
+                break;
+            }
         }
 
-        // TODO: Implement actual Anthropic API call
-        Ok("Anthropic provider not yet implemented".to_string())
+        Err(anyhow::anyhow!("Anthropic stream ended before the reply was complete"))
     }
 
 }
 
+// Ollama Provider
+pub struct OllamaProvider {
+    base_url: String,
+    model: String,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+    keep_alive: Option<i64>,
+    idle_timeout: Duration,
+    client: reqwest::Client,
+}
+
+#[derive(Serialize)]
+struct OllamaRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<i64>,
+    #[serde(skip_serializing_if = "OllamaOptions::is_empty")]
+    options: OllamaOptions,
+}
+
+#[derive(Serialize)]
+struct OllamaOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+}
+
+impl OllamaOptions {
+    fn is_empty(&self) -> bool {
+        self.temperature.is_none() && self.num_predict.is_none()
+    }
+}
+
+/// One `/api/chat` response; when streaming, one line of the body.
+#[derive(Deserialize)]
+struct OllamaResponse {
+    message: Option<OllamaResponseMessage>,
+    #[serde(default)]
+    done: bool,
+    done_reason: Option<String>,
+    prompt_eval_count: Option<u32>,
+    eval_count: Option<u32>,
+    error: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OllamaResponseMessage {
+    #[serde(default)]
+    content: String,
+}
+
+impl OllamaResponse {
+    fn usage(&self) -> Option<Usage> {
+        match (self.prompt_eval_count, self.eval_count) {
+            (None, None) => None,
+            (input, output) => Some(Usage {
+                input_tokens: input.unwrap_or(0),
+                output_tokens: output.unwrap_or(0),
+            }),
+        }
+    }
+
+    fn stop_reason(&self) -> Option<StopReason> {
+        let reason = self.done_reason.clone()?;
+        Some(match reason.as_str() {
+            "stop" => StopReason::EndTurn,
+            "length" => StopReason::MaxTokens,
+            _ => StopReason::Other(reason),
+        })
+    }
+}
+
+/// A model installed in Ollama, from `/api/tags`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaModel {
+    pub name: String,
+    /// Bytes on disk.
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub modified_at: Option<String>,
+    #[serde(default)]
+    pub details: Option<OllamaModelDetails>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaModelDetails {
+    #[serde(default)]
+    pub family: Option<String>,
+    #[serde(default)]
+    pub parameter_size: Option<String>,
+    #[serde(default)]
+    pub quantization_level: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OllamaTags {
+    #[serde(default)]
+    models: Vec<OllamaModel>,
+}
+
+impl Default for OllamaProvider {
+    /// Uses `OLLAMA_HOST` when set, like the Ollama CLI.
+    fn default() -> Self {
+        let base_url = match std::env::var("OLLAMA_HOST") {
+            Ok(host) if host.contains("://") => host,
+            Ok(host) if !host.is_empty() => format!("http://{}", host),
+            _ => "http://127.0.0.1:11434".to_string(),
+        };
+
+        Self {
+            base_url,
+            model: "llama3.2".to_string(),
+            temperature: None,
+            max_tokens: None,
+            keep_alive: None,
+            idle_timeout: Duration::from_secs(IDLE_TIMEOUT_SECS),
+            client: http_client(),
+        }
+    }
+}
+
+impl OllamaProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Server root without `/api`.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f32) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Seconds the model stays loaded after a reply; negative keeps it
+    /// loaded until Ollama stops, 0 unloads it right away. Ollama's own
+    /// default applies when unset.
+    pub fn with_keep_alive_secs(mut self, seconds: i64) -> Self {
+        self.keep_alive = Some(seconds);
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/api/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    fn request(&self, messages: &[ChatMessage], stream: bool) -> reqwest::RequestBuilder {
+        let body = OllamaRequest {
+            model: &self.model,
+            messages,
+            stream,
+            keep_alive: self.keep_alive,
+            options: OllamaOptions {
+                temperature: self.temperature,
+                num_predict: self.max_tokens,
+            },
+        };
+        self.client.post(self.url("chat")).json(&body)
+    }
+
+    /// Models installed on the server.
+    pub async fn list_models(&self) -> Result<Vec<OllamaModel>> {
+        let response = send(self.name(), self.client.get(self.url("tags")), self.idle_timeout).await?;
+        let body = read_body(response, self.idle_timeout).await?;
+        let tags: OllamaTags = serde_json::from_str(&body)
+            .map_err(|e| anyhow::anyhow!("Invalid Ollama model list: {}", e))?;
+        Ok(tags.models)
+    }
+}
+
+#[async_trait::async_trait]
+impl AIProviderTrait for OllamaProvider {
+    fn name(&self) -> &str {
+        "ollama"
+    }
+
+    async fn chat(&self, messages: Vec<ChatMessage>) -> Result<String> {
+        Ok(self.complete(messages).await?.content)
+    }
+
+    async fn complete(&self, messages: Vec<ChatMessage>) -> Result<ChatCompletion> {
+        let response = send(self.name(), self.request(&messages, false), self.idle_timeout).await?;
+        let body = read_body(response, self.idle_timeout).await?;
+
+        let response: OllamaResponse = serde_json::from_str(&body)
+            .map_err(|e| anyhow::anyhow!("Invalid Ollama response: {}", e))?;
+        if let Some(error) = response.error {
+            return Err(anyhow::anyhow!("Ollama error: {}", error));
+        }
+
+        Ok(ChatCompletion {
+            stop_reason: response.stop_reason(),
+            usage: response.usage(),
+            content: response.message.map(|m| m.content).unwrap_or_default(),
+        })
+    }
+
+    async fn stream_completion(
+        &self,
+        messages: Vec<ChatMessage>,
+        events: &mpsc::Sender<StreamEvent>,
+    ) -> Result<ChatCompletion> {
+        let mut response = send(self.name(), self.request(&messages, true), self.idle_timeout).await?;
+        let mut parser: NdjsonParser<OllamaResponse> = NdjsonParser::new();
+        let mut completion = ChatCompletion::default();
+
+        loop {
+            let chunk = within(self.idle_timeout, response.chunk()).await?;
+            let lines = match &chunk {
+                Some(chunk) => parser.push(chunk),
+                None => parser.finish().into_iter().collect(),
+            };
+
+            for line in lines {
+                let line = line.map_err(|e| anyhow::anyhow!("Invalid Ollama stream line: {}", e))?;
+                if let Some(error) = &line.error {
+                    return Err(anyhow::anyhow!("Ollama error: {}", error));
+                }
+                if let Some(message) = &line.message {
+                    send_delta(events, &message.content).await?;
+                    completion.content.push_str(&message.content);
+                }
+                if line.done {
+                    completion.stop_reason = line.stop_reason();
+                    completion.usage = line.usage();
+                    return Ok(completion);
+                }
+            }
+
+            if chunk.is_none() {
+                break;
+            }
+        }
+
+        Err(anyhow::anyhow!("Ollama stream ended before the reply was complete"))
+    }
+}
+
 // Local Provider (Echo for now)
 pub struct LocalProvider;
 
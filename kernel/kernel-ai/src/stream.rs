@@ -0,0 +1,154 @@
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+
+/// Splits a byte stream into lines. Bytes are buffered until a newline, so
+/// a UTF-8 character or line split across chunks comes out whole.
+#[derive(Debug, Default)]
+pub struct LineBuffer {
+    buf: Vec<u8>,
+}
+
+impl LineBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Complete lines in `chunk` and earlier partial input, without their
+    /// `\n` or `\r\n`.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buf.extend_from_slice(chunk);
+
+        let mut lines = Vec::new();
+        while let Some(end) = self.buf.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buf.drain(..=end).collect();
+            lines.push(decode_line(&line[..end]));
+        }
+        lines
+    }
+
+    /// Whatever follows the last newline, once the stream has ended.
+    pub fn finish(&mut self) -> Option<String> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let line = decode_line(&self.buf);
+        self.buf.clear();
+        Some(line)
+    }
+}
+
+fn decode_line(line: &[u8]) -> String {
+    let line = line.strip_suffix(b"\r").unwrap_or(line);
+    String::from_utf8_lossy(line).into_owned()
+}
+
+/// One server-sent event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// The `event:` field, if the server named the event.
+    pub event: Option<String>,
+    /// `data:` lines joined with newlines.
+    pub data: String,
+}
+
+/// Parses `text/event-stream` bodies as used by the OpenAI and Anthropic
+/// streaming APIs. Comments and `id:`/`retry:` fields are ignored.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    lines: LineBuffer,
+    event: Option<String>,
+    data: Vec<String>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events completed by `chunk`.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let lines = self.lines.push(chunk);
+        let mut events = Vec::new();
+        for line in lines {
+            if let Some(event) = self.line(&line) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// The last event, if the stream ended without a blank line after it.
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let last = self.lines.finish().and_then(|line| self.line(&line));
+        last.or_else(|| self.dispatch())
+    }
+
+    fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => self.event = Some(value.to_string()),
+            "data" => self.data.push(value.to_string()),
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        if self.data.is_empty() {
+            self.event = None;
+            return None;
+        }
+        Some(SseEvent {
+            event: self.event.take(),
+            data: std::mem::take(&mut self.data).join("\n"),
+        })
+    }
+}
+
+/// Parses newline-delimited JSON, as streamed by Ollama.
+#[derive(Debug)]
+pub struct NdjsonParser<T> {
+    lines: LineBuffer,
+    _item: PhantomData<T>,
+}
+
+impl<T: DeserializeOwned> NdjsonParser<T> {
+    pub fn new() -> Self {
+        Self {
+            lines: LineBuffer::new(),
+            _item: PhantomData,
+        }
+    }
+
+    /// Values completed by `chunk`; blank lines are skipped.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<serde_json::Result<T>> {
+        self.lines
+            .push(chunk)
+            .iter()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line))
+            .collect()
+    }
+
+    pub fn finish(&mut self) -> Option<serde_json::Result<T>> {
+        self.lines
+            .finish()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(&line))
+    }
+}
+
+impl<T: DeserializeOwned> Default for NdjsonParser<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
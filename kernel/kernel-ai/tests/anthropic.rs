@@ -0,0 +1,95 @@
+mod common;
+
+use common::{MockServer, Reply};
+use kernel_ai::{AIProviderTrait, AnthropicProvider, ApiError, ChatMessage, MessageRole, StopReason, Usage};
+
+fn message(role: MessageRole, content: &str) -> ChatMessage {
+    ChatMessage {
+        role,
+        content: content.to_string(),
+    }
+}
+
+fn provider(server: &MockServer) -> AnthropicProvider {
+    AnthropicProvider::new()
+        .with_api_key("test-key")
+        .with_base_url(server.url.as_str())
+        .with_model("claude-test")
+}
+
+#[tokio::test]
+async fn chat_moves_the_system_prompt_and_merges_turns() {
+    let server = MockServer::start(vec![Reply::json(
+        200,
+        r#"{"content":[{"type":"text","text":"¡Hola"},{"type":"tool_use","id":"t1"},{"type":"text","text":"!"}],
+            "stop_reason":"end_turn","usage":{"input_tokens":3,"output_tokens":2}}"#,
+    )]);
+    let messages = vec![
+        message(MessageRole::System, "Sé breve"),
+        message(MessageRole::User, "hola"),
+        message(MessageRole::User, "¿qué tal?"),
+    ];
+
+    let reply = provider(&server).chat(messages).await.unwrap();
+
+    assert_eq!(reply, "¡Hola!");
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/messages");
+    assert_eq!(request.header("x-api-key"), Some("test-key"));
+    assert_eq!(request.header("anthropic-version"), Some("2023-06-01"));
+    let body = request.json();
+    assert_eq!(body["model"], "claude-test");
+    assert_eq!(body["max_tokens"], 4096);
+    assert_eq!(body["system"], "Sé breve");
+    assert_eq!(body["messages"].as_array().unwrap().len(), 1);
+    assert_eq!(body["messages"][0]["role"], "user");
+    assert_eq!(body["messages"][0]["content"], "hola\n\n¿qué tal?");
+}
+
+#[tokio::test]
+async fn complete_reports_stop_reason_and_usage() {
+    let server = MockServer::start(vec![
+        Reply::json(
+            200,
+            r#"{"content":[{"type":"text","text":"Corta"}],"stop_reason":"max_tokens",
+                "usage":{"input_tokens":11,"output_tokens":16}}"#,
+        ),
+        Reply::json(200, r#"{"content":[],"stop_reason":"refusal","usage":{"input_tokens":4,"output_tokens":0}}"#),
+    ]);
+    let provider = provider(&server).with_max_tokens(16);
+
+    let completion = provider.complete(vec![message(MessageRole::User, "hola")]).await.unwrap();
+    assert_eq!(completion.content, "Corta");
+    assert_eq!(completion.stop_reason, Some(StopReason::MaxTokens));
+    assert_eq!(
+        completion.usage,
+        Some(Usage {
+            input_tokens: 11,
+            output_tokens: 16,
+        })
+    );
+    assert_eq!(server.requests()[0].json()["max_tokens"], 16);
+
+    let refused = provider.complete(vec![message(MessageRole::User, "hola")]).await.unwrap();
+    assert_eq!(refused.content, "");
+    assert_eq!(refused.stop_reason, Some(StopReason::ContentFilter));
+}
+
+#[tokio::test]
+async fn error_status_returns_the_typed_api_error() {
+    let server = MockServer::start(vec![Reply::json(
+        429,
+        r#"{"type":"error","error":{"type":"rate_limit_error","message":"Number of requests has exceeded your rate limit"}}"#,
+    )]);
+
+    let error = provider(&server)
+        .chat(vec![message(MessageRole::User, "hola")])
+        .await
+        .unwrap_err();
+
+    let api_error = error.downcast_ref::<ApiError>().expect("an ApiError");
+    assert_eq!(api_error.provider, "anthropic");
+    assert_eq!(api_error.status, 429);
+    assert_eq!(api_error.kind.as_deref(), Some("rate_limit_error"));
+    assert_eq!(api_error.message, "Number of requests has exceeded your rate limit");
+}
@@ -0,0 +1,161 @@
+//! A minimal HTTP server that answers each connection with the next canned
+//! reply and records what the provider sent.
+
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+
+/// A response written with chunked transfer encoding, one chunk per part,
+/// so the client sees the body arrive in pieces.
+pub struct Reply {
+    status: u16,
+    content_type: String,
+    parts: Vec<(Duration, Vec<u8>)>,
+}
+
+impl Reply {
+    pub fn json(status: u16, body: &str) -> Self {
+        Self::new(status, "application/json").part(body)
+    }
+
+    /// Server-sent events, one chunk per entry. Entries may split an event
+    /// or a UTF-8 character anywhere.
+    pub fn event_stream(parts: &[&[u8]]) -> Self {
+        parts.iter().fold(Self::new(200, "text/event-stream"), |reply, part| reply.part(part))
+    }
+
+    pub fn ndjson(parts: &[&[u8]]) -> Self {
+        parts.iter().fold(Self::new(200, "application/x-ndjson"), |reply, part| reply.part(part))
+    }
+
+    pub fn new(status: u16, content_type: &str) -> Self {
+        Self {
+            status,
+            content_type: content_type.to_string(),
+            parts: Vec::new(),
+        }
+    }
+
+    pub fn part(self, part: impl AsRef<[u8]>) -> Self {
+        self.part_after(Duration::ZERO, part)
+    }
+
+    /// Sends `part` after `pause`, to simulate a stalled server.
+    pub fn part_after(mut self, pause: Duration, part: impl AsRef<[u8]>) -> Self {
+        self.parts.push((pause, part.as_ref().to_vec()));
+        self
+    }
+}
+
+/// A request as the server received it.
+#[derive(Debug, Clone)]
+pub struct Recorded {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Recorded {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn json(&self) -> Value {
+        serde_json::from_str(&self.body).unwrap()
+    }
+}
+
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<Recorded>>>,
+}
+
+impl MockServer {
+    /// Serves `replies` in order, one per connection.
+    pub fn start(replies: Vec<Reply>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let recorded = requests.clone();
+        thread::spawn(move || {
+            for reply in replies {
+                let Ok((stream, _)) = listener.accept() else { return };
+                serve(stream, reply, &recorded);
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    pub fn requests(&self) -> Vec<Recorded> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+/// Records the request before replying, so it is there as soon as the
+/// client has an answer.
+fn serve(stream: TcpStream, reply: Reply, recorded: &Mutex<Vec<Recorded>>) -> Option<()> {
+    let mut reader = BufReader::new(stream.try_clone().ok()?);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut words = request_line.split_whitespace();
+    let method = words.next()?.to_string();
+    let path = words.next()?.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let length = headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, v)| v.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+
+    recorded.lock().unwrap().push(Recorded {
+        method,
+        path,
+        headers,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    });
+
+    let mut stream = stream;
+    let head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Type: {}\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n",
+        reply.status, reply.content_type
+    );
+    let _ = stream.write_all(head.as_bytes());
+    for (pause, part) in &reply.parts {
+        let _ = stream.flush();
+        thread::sleep(*pause);
+        let _ = write!(stream, "{:x}\r\n", part.len());
+        let _ = stream.write_all(part);
+        let _ = stream.write_all(b"\r\n");
+    }
+    let _ = stream.write_all(b"0\r\n\r\n");
+    let _ = stream.flush();
+
+    Some(())
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use common::{MockServer, Reply};
+use kernel_ai::{ChatMessage, MessageRole, ProviderConfig};
+use serde_json::json;
+
+fn config(value: serde_json::Value) -> ProviderConfig {
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn openai_compatible_requires_a_base_url() {
+    for base_url in [None, Some(""), Some("  ")] {
+        let config = config(json!({ "provider": "openai_compatible", "name": "lm-studio", "base_url": base_url }));
+        let error = config.build().err().expect("a config error");
+        assert!(error.to_string().contains("lm-studio"), "{}", error);
+    }
+}
+
+#[test]
+fn openai_compatible_takes_the_name_from_the_config() {
+    let named = config(json!({ "provider": "openai_compatible", "name": "LM Studio", "base_url": "http://localhost:1234/v1" }));
+    assert_eq!(named.build().unwrap().name(), "LM Studio");
+
+    let unnamed = config(json!({ "provider": "openai_compatible", "name": "", "base_url": "http://localhost:1234/v1" }));
+    assert_eq!(unnamed.build().unwrap().name(), "openai_compatible");
+}
+
+#[tokio::test]
+async fn ollama_takes_keep_alive_from_the_config() {
+    let server = MockServer::start(vec![
+        Reply::json(200, r#"{"message":{"role":"assistant","content":"ok"},"done":true}"#),
+        Reply::json(200, r#"{"message":{"role":"assistant","content":"ok"},"done":true}"#),
+    ]);
+    let messages = || {
+        vec![ChatMessage {
+            role: MessageRole::User,
+            content: "hola".to_string(),
+        }]
+    };
+
+    let kept = config(json!({ "provider": "ollama", "base_url": server.url, "keep_alive_secs": -1 }));
+    kept.build().unwrap().chat(messages()).await.unwrap();
+    let unset = config(json!({ "provider": "ollama", "base_url": server.url }));
+    unset.build().unwrap().chat(messages()).await.unwrap();
+
+    let requests = server.requests();
+    assert_eq!(requests[0].json()["keep_alive"], -1);
+    assert!(requests[1].json().get("keep_alive").is_none());
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use common::{MockServer, Reply};
+use kernel_ai::{AIProviderTrait, ApiError, ChatMessage, MessageRole, OllamaProvider, StopReason, StreamEvent, Usage};
+use tokio::sync::mpsc;
+
+fn messages() -> Vec<ChatMessage> {
+    vec![ChatMessage {
+        role: MessageRole::User,
+        content: "hola".to_string(),
+    }]
+}
+
+fn provider(server: &MockServer) -> OllamaProvider {
+    OllamaProvider::new().with_base_url(server.url.as_str()).with_model("llama-test")
+}
+
+#[tokio::test]
+async fn list_models_reads_installed_models() {
+    let server = MockServer::start(vec![Reply::json(
+        200,
+        r#"{"models":[
+            {"name":"llama3.2:latest","model":"llama3.2:latest","size":2019393189,"modified_at":"2024-10-01T10:00:00Z",
+             "details":{"family":"llama","parameter_size":"3.2B","quantization_level":"Q4_K_M"}},
+            {"name":"qwen2.5-coder:7b"}
+        ]}"#,
+    )]);
+
+    let models = provider(&server).list_models().await.unwrap();
+
+    assert_eq!(server.requests()[0].method, "GET");
+    assert_eq!(server.requests()[0].path, "/api/tags");
+    assert_eq!(models.len(), 2);
+    assert_eq!(models[0].name, "llama3.2:latest");
+    assert_eq!(models[0].size, 2019393189);
+    let details = models[0].details.as_ref().unwrap();
+    assert_eq!(details.parameter_size.as_deref(), Some("3.2B"));
+    assert_eq!(details.quantization_level.as_deref(), Some("Q4_K_M"));
+    assert_eq!(models[1].name, "qwen2.5-coder:7b");
+    assert_eq!(models[1].size, 0);
+    assert!(models[1].details.is_none());
+}
+
+#[tokio::test]
+async fn list_models_reports_server_errors() {
+    let server = MockServer::start(vec![Reply::json(500, r#"{"error":"llama runner process has terminated"}"#)]);
+
+    let error = provider(&server).list_models().await.unwrap_err();
+
+    let api_error = error.downcast_ref::<ApiError>().expect("an ApiError");
+    assert_eq!(api_error.status, 500);
+    assert_eq!(api_error.message, "llama runner process has terminated");
+}
+
+#[tokio::test]
+async fn complete_reports_stop_reason_and_usage() {
+    let server = MockServer::start(vec![Reply::json(
+        200,
+        r#"{"model":"llama-test","message":{"role":"assistant","content":"¡Hola!"},"done":true,
+            "done_reason":"length","prompt_eval_count":7,"eval_count":3}"#,
+    )]);
+
+    let completion = provider(&server)
+        .with_temperature(0.2)
+        .with_max_tokens(3)
+        .with_keep_alive_secs(-1)
+        .complete(messages())
+        .await
+        .unwrap();
+
+    assert_eq!(completion.content, "¡Hola!");
+    assert_eq!(completion.stop_reason, Some(StopReason::MaxTokens));
+    assert_eq!(
+        completion.usage,
+        Some(Usage {
+            input_tokens: 7,
+            output_tokens: 3,
+        })
+    );
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/api/chat");
+    let body = request.json();
+    assert_eq!(body["model"], "llama-test");
+    assert_eq!(body["stream"], false);
+    assert_eq!(body["keep_alive"], -1);
+    assert_eq!(body["options"]["num_predict"], 3);
+}
+
+#[tokio::test]
+async fn stream_reassembles_lines_split_across_chunks() {
+    let server = MockServer::start(vec![Reply::ndjson(&[
+        b"{\"message\":{\"content\":\"Caf\"},\"done\":false}\n{\"message\":{\"content\":\"\xc3",
+        b"\xa9\"},\"done\":false}\n{\"message\":{\"content\":\" listo\"},\"do",
+        b"ne\":false}\n",
+        b"{\"message\":{\"content\":\"\"},\"done\":true,\"done_reason\":\"stop\",\"prompt_eval_count\":4,\"eval_count\":3}\n",
+    ])]);
+    let (tx, mut rx) = mpsc::channel(64);
+
+    provider(&server).stream_chat(messages(), tx).await;
+
+    let mut deltas = String::new();
+    let mut last = None;
+    while let Some(event) = rx.recv().await {
+        match event {
+            StreamEvent::Delta { text } => deltas.push_str(&text),
+            event => last = Some(event),
+        }
+    }
+    assert_eq!(deltas, "Café listo");
+    assert_eq!(
+        last,
+        Some(StreamEvent::Done {
+            stop_reason: Some(StopReason::EndTurn),
+            usage: Some(Usage {
+                input_tokens: 4,
+                output_tokens: 3,
+            }),
+        })
+    );
+    assert_eq!(server.requests()[0].json()["stream"], true);
+}
+
+#[tokio::test]
+async fn stream_error_line_ends_the_stream() {
+    let server = MockServer::start(vec![Reply::ndjson(&[
+        b"{\"message\":{\"content\":\"Hola\"},\"done\":false}\n",
+        b"{\"error\":\"model requires more system memory\"}\n",
+    ])]);
+    let (tx, mut rx) = mpsc::channel(64);
+
+    provider(&server).stream_chat(messages(), tx).await;
+
+    let mut events = Vec::new();
+    while let Some(event) = rx.recv().await {
+        events.push(event);
+    }
+    match events.last() {
+        Some(StreamEvent::Error { message, .. }) => assert!(message.contains("more system memory"), "{}", message),
+        other => panic!("unexpected last event {:?}", other),
+    }
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use common::{MockServer, Reply};
+use kernel_ai::{AIProviderTrait, ApiError, ChatMessage, MessageRole, OpenAIProvider, StopReason, Usage};
+
+fn messages() -> Vec<ChatMessage> {
+    vec![
+        ChatMessage {
+            role: MessageRole::System,
+            content: "Responde en español".to_string(),
+        },
+        ChatMessage {
+            role: MessageRole::User,
+            content: "hola".to_string(),
+        },
+    ]
+}
+
+fn provider(server: &MockServer) -> OpenAIProvider {
+    OpenAIProvider::new()
+        .with_api_key("test-key")
+        .with_base_url(format!("{}/v1/", server.url))
+        .with_model("gpt-test")
+}
+
+#[tokio::test]
+async fn chat_sends_the_conversation_and_returns_the_reply() {
+    let server = MockServer::start(vec![Reply::json(
+        200,
+        r#"{"choices":[{"message":{"role":"assistant","content":"¡Hola!"},"finish_reason":"stop"}]}"#,
+    )]);
+
+    let reply = provider(&server).with_temperature(0.5).chat(messages()).await.unwrap();
+
+    assert_eq!(reply, "¡Hola!");
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/v1/chat/completions");
+    assert_eq!(request.header("authorization"), Some("Bearer test-key"));
+    let body = request.json();
+    assert_eq!(body["model"], "gpt-test");
+    assert_eq!(body["temperature"], 0.5);
+    assert_eq!(body["messages"][0]["role"], "system");
+    assert_eq!(body["messages"][1]["content"], "hola");
+    assert!(body.get("stream").is_none());
+    assert!(body.get("max_tokens").is_none());
+}
+
+#[tokio::test]
+async fn complete_reports_stop_reason_and_usage() {
+    let server = MockServer::start(vec![Reply::json(
+        200,
+        r#"{"choices":[{"message":{"content":"Una respuesta cort"},"finish_reason":"length"}],
+            "usage":{"prompt_tokens":12,"completion_tokens":8,"total_tokens":20}}"#,
+    )]);
+
+    let completion = provider(&server).with_max_tokens(8).complete(messages()).await.unwrap();
+
+    assert_eq!(completion.content, "Una respuesta cort");
+    assert_eq!(completion.stop_reason, Some(StopReason::MaxTokens));
+    assert_eq!(
+        completion.usage,
+        Some(Usage {
+            input_tokens: 12,
+            output_tokens: 8,
+        })
+    );
+    assert_eq!(server.requests()[0].json()["max_tokens"], 8);
+}
+
+#[tokio::test]
+async fn filtered_reply_without_content_is_empty() {
+    let server = MockServer::start(vec![Reply::json(
+        200,
+        r#"{"choices":[{"message":{"content":null},"finish_reason":"content_filter"}]}"#,
+    )]);
+
+    let completion = provider(&server).complete(messages()).await.unwrap();
+
+    assert_eq!(completion.content, "");
+    assert_eq!(completion.stop_reason, Some(StopReason::ContentFilter));
+    assert_eq!(completion.usage, None);
+}
+
+#[tokio::test]
+async fn error_status_returns_the_typed_api_error() {
+    let server = MockServer::start(vec![Reply::json(
+        401,
+        r#"{"error":{"message":"Incorrect API key provided","type":"invalid_request_error","code":"invalid_api_key"}}"#,
+    )]);
+
+    let error = provider(&server).chat(messages()).await.unwrap_err();
+
+    let api_error = error.downcast_ref::<ApiError>().expect("an ApiError");
+    assert_eq!(api_error.provider, "openai");
+    assert_eq!(api_error.status, 401);
+    assert_eq!(api_error.kind.as_deref(), Some("invalid_request_error"));
+    assert_eq!(api_error.message, "Incorrect API key provided");
+}
+
+#[tokio::test]
+async fn error_status_without_a_json_body_keeps_the_body() {
+    let server = MockServer::start(vec![Reply::new(502, "text/html").part("<html>Bad Gateway</html>")]);
+
+    let error = provider(&server).chat(messages()).await.unwrap_err();
+
+    let api_error = error.downcast_ref::<ApiError>().expect("an ApiError");
+    assert_eq!(api_error.status, 502);
+    assert_eq!(api_error.kind, None);
+    assert_eq!(api_error.message, "<html>Bad Gateway</html>");
+}
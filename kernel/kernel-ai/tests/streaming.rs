@@ -0,0 +1,145 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{MockServer, Reply};
+use kernel_ai::{
+    AIProviderTrait, AnthropicProvider, ChatMessage, MessageRole, OpenAICompatibleProvider, StopReason, StreamEvent,
+    Usage,
+};
+use tokio::sync::mpsc;
+
+fn user(content: &str) -> Vec<ChatMessage> {
+    vec![ChatMessage {
+        role: MessageRole::User,
+        content: content.to_string(),
+    }]
+}
+
+async fn stream(provider: &dyn AIProviderTrait) -> Vec<StreamEvent> {
+    let (tx, mut rx) = mpsc::channel(64);
+    provider.stream_chat(user("hola"), tx).await;
+
+    let mut events = Vec::new();
+    while let Some(event) = rx.recv().await {
+        events.push(event);
+    }
+    events
+}
+
+fn text(events: &[StreamEvent]) -> String {
+    events
+        .iter()
+        .filter_map(|e| match e {
+            StreamEvent::Delta { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn openai_stream_reassembles_split_events_and_characters() {
+    let server = MockServer::start(vec![Reply::event_stream(&[
+        b"data: {\"choices\":[{\"delta\":{\"content\":\"Caf",
+        b"\xc3",
+        b"\xa9\"}}]}\n\ndata: {\"choices\":[{\"delta\":{\"content\":\" listo\"},\"finish_reason\":\"stop\"}]}\n",
+        b"\ndata: {\"choices\":[],\"usage\":{\"prompt_tokens\":5,\"completion_tokens\":2}}\n\n",
+        b"data: [DONE]\n\n",
+    ])]);
+    let provider = OpenAICompatibleProvider::new("mock", format!("{}/v1", server.url));
+
+    let events = stream(&provider).await;
+
+    assert_eq!(text(&events), "Café listo");
+    assert_eq!(
+        events.last(),
+        Some(&StreamEvent::Done {
+            stop_reason: Some(StopReason::EndTurn),
+            usage: Some(Usage {
+                input_tokens: 5,
+                output_tokens: 2,
+            }),
+        })
+    );
+    let request = &server.requests()[0];
+    assert_eq!(request.path, "/v1/chat/completions");
+    assert_eq!(request.json()["stream"], true);
+}
+
+#[tokio::test]
+async fn anthropic_stream_reports_stop_reason_and_usage() {
+    let server = MockServer::start(vec![Reply::event_stream(&[
+        b"event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":9}}}\n\n",
+        b"event: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"delta\":{\"type\":\"text_delta\",\"text\":\"Hola\"}}\n\nevent: ping\n",
+        b"data: {\"type\":\"ping\"}\n\nevent: content_block_delta\ndata: {\"type\":\"content_block_delta\",\"delta\":{\"text\":\" mundo\"}}\n\n",
+        b"event: message_delta\ndata: {\"type\":\"message_delta\",\"delta\":{\"stop_reason\":\"max_tokens\"},\"usage\":{\"output_tokens\":4}}\n\n",
+        b"event: message_stop\ndata: {\"type\":\"message_stop\"}\n\n",
+    ])]);
+    let provider = AnthropicProvider::new().with_api_key("test-key").with_base_url(server.url.as_str());
+
+    let events = stream(&provider).await;
+
+    assert_eq!(text(&events), "Hola mundo");
+    assert_eq!(
+        events.last(),
+        Some(&StreamEvent::Done {
+            stop_reason: Some(StopReason::MaxTokens),
+            usage: Some(Usage {
+                input_tokens: 9,
+                output_tokens: 4,
+            }),
+        })
+    );
+}
+
+#[tokio::test]
+async fn stream_error_event_ends_the_stream() {
+    let server = MockServer::start(vec![Reply::event_stream(&[
+        b"data: {\"choices\":[{\"delta\":{\"content\":\"Hola\"}}]}\n\n",
+        b"data: {\"error\":{\"message\":\"Overloaded\",\"type\":\"server_error\"}}\n\n",
+    ])]);
+    let provider = OpenAICompatibleProvider::new("mock", server.url.as_str());
+
+    let events = stream(&provider).await;
+
+    assert_eq!(text(&events), "Hola");
+    match events.last() {
+        Some(StreamEvent::Error { status, kind, message }) => {
+            assert_eq!(*status, Some(200));
+            assert_eq!(kind.as_deref(), Some("server_error"));
+            assert!(message.contains("Overloaded"), "{}", message);
+        }
+        other => panic!("unexpected last event {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn slow_stream_outlasts_the_timeout_while_it_keeps_arriving() {
+    let pause = Duration::from_millis(400);
+    let server = MockServer::start(vec![Reply::new(200, "text/event-stream")
+        .part_after(pause, "data: {\"choices\":[{\"delta\":{\"content\":\"a\"}}]}\n\n")
+        .part_after(pause, "data: {\"choices\":[{\"delta\":{\"content\":\"b\"}}]}\n\n")
+        .part_after(pause, "data: {\"choices\":[{\"delta\":{\"content\":\"c\"},\"finish_reason\":\"stop\"}]}\n\n")
+        .part("data: [DONE]\n\n")]);
+    let provider = OpenAICompatibleProvider::new("mock", server.url.as_str()).with_timeout(Duration::from_secs(1));
+
+    let events = stream(&provider).await;
+
+    assert_eq!(text(&events), "abc");
+    assert!(matches!(events.last(), Some(StreamEvent::Done { .. })), "{:?}", events);
+}
+
+#[tokio::test]
+async fn stalled_stream_fails_after_the_timeout() {
+    let server = MockServer::start(vec![Reply::new(200, "text/event-stream")
+        .part("data: {\"choices\":[{\"delta\":{\"content\":\"Hola\"}}]}\n\n")
+        .part_after(Duration::from_secs(5), "data: [DONE]\n\n")]);
+    let provider = OpenAICompatibleProvider::new("mock", server.url.as_str()).with_timeout(Duration::from_secs(1));
+
+    let started = std::time::Instant::now();
+    let events = stream(&provider).await;
+
+    assert!(started.elapsed() < Duration::from_secs(4));
+    assert_eq!(text(&events), "Hola");
+    assert!(matches!(events.last(), Some(StreamEvent::Error { status: None, .. })), "{:?}", events);
+}
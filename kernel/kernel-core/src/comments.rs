@@ -0,0 +1,178 @@
+use std::ops::Range;
+
+use crate::language::LanguageDefinition;
+
+/// One line of comment text. Block comments spanning several lines produce
+/// one entry per line so positions stay exact.
+#[derive(Debug, Clone)]
+pub struct Comment {
+    /// 1-based line.
+    pub line: u32,
+    /// Byte column where `text` starts, after the comment marker.
+    pub column: u32,
+    pub text: String,
+    /// Byte range in the source covered by this line of the comment,
+    /// including its markers.
+    pub range: Range<usize>,
+}
+
+/// Extracts the comments of `content`, skipping anything inside string
+/// literals, using the comment and string syntax of `language`.
+pub fn extract_comments(content: &str, language: &LanguageDefinition) -> Vec<Comment> {
+    scan(content, language).0
+}
+
+/// Single pass over `content` returning its comments and the byte ranges of
+/// string and character literal contents (without delimiters).
+fn scan(content: &str, language: &LanguageDefinition) -> (Vec<Comment>, Vec<Range<usize>>) {
+    let bytes = content.as_bytes();
+    let mut comments = Vec::new();
+    let mut literals = Vec::new();
+
+    let mut line_comments: Vec<&str> = language.line_comments.iter().map(|c| c.as_str()).collect();
+    line_comments.sort_by_key(|c| std::cmp::Reverse(c.len()));
+    let mut strings: Vec<&str> = language.strings.iter().map(|s| s.as_str()).collect();
+    strings.sort_by_key(|s| std::cmp::Reverse(s.len()));
+
+    let mut i = 0;
+    let mut line = 1u32;
+    let mut line_start = 0usize;
+
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+
+        if rest[0] == b'\n' {
+            i += 1;
+            line += 1;
+            line_start = i;
+            continue;
+        }
+
+        if let Some(marker) = line_comments.iter().find(|m| rest.starts_with(m.as_bytes())) {
+            let start = i + marker.len();
+            let end = find_byte(bytes, start, b'\n').unwrap_or(bytes.len());
+            comments.push(Comment {
+                line,
+                column: (start - line_start) as u32,
+                text: content[start..end].trim_end_matches('\r').to_string(),
+                range: i..end,
+            });
+            i = end;
+            continue;
+        }
+
+        if let Some(block) = &language.block_comment {
+            if !block.start.is_empty() && rest.starts_with(block.start.as_bytes()) {
+                let mut start = i + block.start.len();
+                let mut range_start = i;
+                let end = find_seq(bytes, start, block.end.as_bytes()).unwrap_or(bytes.len());
+                let block_end = (end + block.end.len()).min(bytes.len());
+
+                // Emit one comment per line of the block
+                loop {
+                    let line_end = find_byte(bytes, start, b'\n').filter(|&n| n < end);
+                    let segment_end = line_end.unwrap_or(end);
+                    comments.push(Comment {
+                        line,
+                        column: (start - line_start) as u32,
+                        text: content[start..segment_end].trim_end_matches('\r').to_string(),
+                        range: range_start..line_end.unwrap_or(block_end),
+                    });
+                    match line_end {
+                        Some(n) => {
+                            line += 1;
+                            line_start = n + 1;
+                            start = n + 1;
+                            range_start = n + 1;
+                        }
+                        None => break,
+                    }
+                }
+
+                i = block_end;
+                continue;
+            }
+        }
+
+        if let Some(delimiter) = strings.iter().find(|d| rest.starts_with(d.as_bytes())) {
+            let mut j = i + delimiter.len();
+            while j < bytes.len() && !bytes[j..].starts_with(delimiter.as_bytes()) {
+                if bytes[j] == b'\\' && j + 1 < bytes.len() {
+                    j += 1;
+                }
+                if bytes[j] == b'\n' {
+                    line += 1;
+                    line_start = j + 1;
+                }
+                j += 1;
+            }
+            literals.push(i + delimiter.len()..j.min(bytes.len()));
+            i = (j + delimiter.len()).min(bytes.len());
+            continue;
+        }
+
+        // Character literals such as '"' in languages where ' is not a string delimiter
+        if rest[0] == b'\'' {
+            if rest.len() >= 3 && rest[1] != b'\\' && rest[2] == b'\'' {
+                literals.push(i + 1..i + 2);
+                i += 3;
+                continue;
+            }
+            if rest.len() >= 4 && rest[1] == b'\\' && rest[3] == b'\'' {
+                literals.push(i + 1..i + 3);
+                i += 4;
+                continue;
+            }
+        }
+
+        i += 1;
+    }
+
+    (comments, literals)
+}
+
+/// Returns `content` with every comment replaced by spaces, keeping line
+/// breaks so line numbers and columns are unchanged.
+pub fn strip_comments(content: &str, language: &LanguageDefinition) -> String {
+    let (comments, _) = scan(content, language);
+    blank(content, comments.into_iter().map(|c| c.range))
+}
+
+/// Like `strip_comments`, but also blanks the contents of string literals so
+/// brackets and keywords inside them are not mistaken for code.
+pub fn strip_comments_and_strings(content: &str, language: &LanguageDefinition) -> String {
+    let (comments, literals) = scan(content, language);
+    blank(content, comments.into_iter().map(|c| c.range).chain(literals))
+}
+
+fn blank(content: &str, ranges: impl Iterator<Item = Range<usize>>) -> String {
+    let mut bytes = content.as_bytes().to_vec();
+    for range in ranges {
+        for b in &mut bytes[range] {
+            if *b != b'\n' && *b != b'\r' {
+                *b = b' ';
+            }
+        }
+    }
+
+    // Ranges start and end on ASCII markers or line breaks, so any multi-byte
+    // character is blanked completely and the result stays UTF-8.
+    String::from_utf8(bytes).unwrap_or_else(|_| content.to_string())
+}
+
+fn find_byte(bytes: &[u8], from: usize, needle: u8) -> Option<usize> {
+    bytes[from.min(bytes.len())..]
+        .iter()
+        .position(|&b| b == needle)
+        .map(|p| from + p)
+}
+
+fn find_seq(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() || from >= bytes.len() {
+        return None;
+    }
+    bytes[from..]
+        .windows(needle.len())
+        .position(|w| w == needle)
+        .map(|p| from + p)
+}
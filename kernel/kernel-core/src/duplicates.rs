@@ -0,0 +1,270 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::comments::strip_comments;
+use crate::indexer::{IndexedFile, ProjectIndexer};
+use crate::workspace::SearchScope;
+
+const HASH_BASE: u64 = 1_000_003;
+
+/// Keywords kept verbatim when identifiers are normalized, so `if x` and
+/// `for x` never look alike.
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "case", "catch", "class", "const", "continue", "def", "default",
+    "do", "elif", "else", "enum", "except", "export", "extends", "fn", "for", "from", "func",
+    "function", "if", "impl", "import", "in", "interface", "let", "loop", "match", "mod", "module",
+    "mut", "new", "pub", "return", "self", "static", "struct", "switch", "this", "throw", "trait",
+    "try", "type", "use", "val", "var", "while", "yield",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DuplicateConfig {
+    /// Minimum clone length in normalized tokens.
+    pub min_tokens: usize,
+    /// Minimum clone length in lines.
+    pub min_lines: u32,
+    /// Treat identifiers and literals as equal, so renamed copies still match.
+    pub normalize_identifiers: bool,
+    #[serde(skip)]
+    pub scope: SearchScope,
+}
+
+impl Default for DuplicateConfig {
+    fn default() -> Self {
+        Self {
+            min_tokens: 50,
+            min_lines: 5,
+            normalize_identifiers: true,
+            scope: SearchScope::All,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloneLocation {
+    pub path: PathBuf,
+    pub relative_path: PathBuf,
+    /// 1-based, inclusive line range.
+    pub start_line: u32,
+    pub end_line: u32,
+}
+
+/// A block of code that appears in two or more places.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CloneGroup {
+    pub tokens: usize,
+    pub lines: u32,
+    pub locations: Vec<CloneLocation>,
+}
+
+struct TokenizedFile<'a> {
+    file: &'a IndexedFile,
+    tokens: Vec<u64>,
+    lines: Vec<u32>,
+}
+
+impl ProjectIndexer {
+    /// Finds blocks of at least `config.min_tokens` normalized tokens that
+    /// occur more than once across the indexed files. Comments are ignored.
+    pub async fn find_duplicates(&self, config: &DuplicateConfig) -> Vec<CloneGroup> {
+        let window = config.min_tokens.max(1);
+        let mut files = self.files_in(&config.scope).await;
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+        let tokenized: Vec<TokenizedFile> = files
+            .iter()
+            .map(|file| {
+                let code = match self.languages().get(&file.language) {
+                    Some(language) => strip_comments(&file.content, language),
+                    None => file.content.clone(),
+                };
+                let (tokens, lines) = normalize(&code, config.normalize_identifiers);
+                TokenizedFile { file, tokens, lines }
+            })
+            .collect();
+
+        // Every window start, bucketed by its rolling hash
+        let mut buckets: HashMap<u64, Vec<(usize, usize)>> = HashMap::new();
+        for (f, tf) in tokenized.iter().enumerate() {
+            for (pos, hash) in window_hashes(&tf.tokens, window) {
+                buckets.entry(hash).or_default().push((f, pos));
+            }
+        }
+
+        let mut covered: HashSet<(usize, usize)> = HashSet::new();
+        let mut groups = Vec::new();
+
+        for (f, tf) in tokenized.iter().enumerate() {
+            for (pos, hash) in window_hashes(&tf.tokens, window) {
+                if covered.contains(&(f, pos)) {
+                    continue;
+                }
+                let bucket = match buckets.get(&hash) {
+                    Some(bucket) if bucket.len() > 1 => bucket,
+                    _ => continue,
+                };
+
+                let anchor = &tf.tokens[pos..pos + window];
+                let mut members = vec![(f, pos)];
+                for &(other_f, other_pos) in bucket {
+                    if covered.contains(&(other_f, other_pos)) || members.iter().any(|&m| overlaps(m, (other_f, other_pos), window)) {
+                        continue;
+                    }
+                    if tokenized[other_f].tokens[other_pos..other_pos + window] == *anchor {
+                        members.push((other_f, other_pos));
+                    }
+                }
+                if members.len() < 2 {
+                    continue;
+                }
+
+                // Extend the clone while every copy keeps matching
+                let mut length = window;
+                while let Some(next) = tf.tokens.get(pos + length) {
+                    let all_match = members.iter().all(|&(mf, mp)| {
+                        tokenized[mf].tokens.get(mp + length) == Some(next)
+                            && members.iter().all(|&other| other == (mf, mp) || !overlaps(other, (mf, mp), length + 1))
+                    });
+                    if !all_match {
+                        break;
+                    }
+                    length += 1;
+                }
+
+                let mut locations = Vec::new();
+                for &(mf, mp) in &members {
+                    for start in mp..=(mp + length - window) {
+                        covered.insert((mf, start));
+                    }
+                    let member = &tokenized[mf];
+                    locations.push(CloneLocation {
+                        path: member.file.path.clone(),
+                        relative_path: member.file.relative_path.clone(),
+                        start_line: member.lines[mp],
+                        end_line: member.lines[mp + length - 1],
+                    });
+                }
+
+                let lines = locations
+                    .iter()
+                    .map(|l| l.end_line - l.start_line + 1)
+                    .min()
+                    .unwrap_or(0);
+                if lines >= config.min_lines {
+                    groups.push(CloneGroup {
+                        tokens: length,
+                        lines,
+                        locations,
+                    });
+                }
+            }
+        }
+
+        groups.sort_by_key(|g| std::cmp::Reverse(g.tokens));
+        groups
+    }
+}
+
+/// Two occurrences overlap when they are in the same file and their token
+/// ranges of `length` intersect.
+fn overlaps(a: (usize, usize), b: (usize, usize), length: usize) -> bool {
+    a.0 == b.0 && a.1 < b.1 + length && b.1 < a.1 + length
+}
+
+/// Splits code into hashed tokens and the 1-based line of each token.
+fn normalize(code: &str, normalize_identifiers: bool) -> (Vec<u64>, Vec<u32>) {
+    let mut tokens = Vec::new();
+    let mut lines = Vec::new();
+    let chars: Vec<char> = code.chars().collect();
+    let mut line = 1u32;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        let start_line = line;
+        let token: String = if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect();
+            if normalize_identifiers && !KEYWORDS.contains(&word.as_str()) {
+                "$id".to_string()
+            } else {
+                word
+            }
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.' || chars[i] == '_') {
+                i += 1;
+            }
+            if normalize_identifiers {
+                "$lit".to_string()
+            } else {
+                chars[start..i].iter().collect()
+            }
+        } else if c == '"' || c == '\'' || c == '`' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != c && chars[i] != '\n' {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            if normalize_identifiers {
+                "$lit".to_string()
+            } else {
+                chars[start..i].iter().collect()
+            }
+        } else {
+            i += 1;
+            c.to_string()
+        };
+
+        let mut hasher = DefaultHasher::new();
+        token.hash(&mut hasher);
+        tokens.push(hasher.finish());
+        lines.push(start_line);
+    }
+
+    (tokens, lines)
+}
+
+/// Polynomial rolling hash of every window of `size` tokens.
+fn window_hashes(tokens: &[u64], size: usize) -> Vec<(usize, u64)> {
+    if tokens.len() < size {
+        return Vec::new();
+    }
+
+    let top = (1..size).fold(1u64, |acc, _| acc.wrapping_mul(HASH_BASE));
+    let mut hash = tokens[..size]
+        .iter()
+        .fold(0u64, |acc, &t| acc.wrapping_mul(HASH_BASE).wrapping_add(t));
+    let mut hashes = vec![(0, hash)];
+
+    for pos in 1..=(tokens.len() - size) {
+        hash = hash
+            .wrapping_sub(tokens[pos - 1].wrapping_mul(top))
+            .wrapping_mul(HASH_BASE)
+            .wrapping_add(tokens[pos + size - 1]);
+        hashes.push((pos, hash));
+    }
+
+    hashes
+}
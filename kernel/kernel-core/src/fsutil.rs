@@ -0,0 +1,25 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Writes through a sibling temp file and renames it over `path`, so a crash
+/// never leaves a half-written file. Missing parent directories are created.
+pub(crate) fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Invalid file path {:?}", path)))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_file_name(format!(".{}.meacode-tmp", file_name));
+
+    fs::write(&tmp, contents)?;
+    if let Err(e) = fs::rename(&tmp, path) {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+use tokio::process::Command;
+
+use crate::indexer::ProjectIndexer;
+use crate::workspace::SearchScope;
+
+/// Which files of a git repository a search should be limited to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum GitScope {
+    /// Files in the repository index.
+    Tracked,
+    /// Files with changes staged for commit.
+    Staged,
+    /// Files that differ from the merge base with `base`, including
+    /// uncommitted and untracked changes, like a pull request would show.
+    Changed { base: String },
+}
+
+/// Absolute paths of the tracked files under `dir`.
+pub async fn tracked_files(dir: &Path) -> anyhow::Result<HashSet<PathBuf>> {
+    // Without --full-name, ls-files lists paths relative to `dir` and only below it
+    let listed = git(dir, &["ls-files", "-z"]).await?;
+    Ok(split_paths(&listed).map(|p| dir.join(p)).collect())
+}
+
+/// Absolute paths of the files under `dir` with changes staged for commit.
+pub async fn staged_files(dir: &Path) -> anyhow::Result<HashSet<PathBuf>> {
+    // --relative keeps paths relative to `dir` and drops changes outside it
+    let listed = git(dir, &["diff", "--cached", "--relative", "--name-only", "-z", "--diff-filter=d"]).await?;
+    Ok(split_paths(&listed).map(|p| dir.join(p)).collect())
+}
+
+/// Absolute paths of the files under `dir` that changed relative to the
+/// merge base of `base` and `HEAD`, plus untracked files that are not ignored.
+pub async fn changed_files(dir: &Path, base: &str) -> anyhow::Result<HashSet<PathBuf>> {
+    // `base` comes from the caller; one starting with `-` would be read as an option
+    if base.is_empty() || base.starts_with('-') {
+        anyhow::bail!("Invalid git revision {:?}", base);
+    }
+    let commit = git(dir, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", base)])
+        .await
+        .map_err(|_| anyhow::anyhow!("Unknown git revision {:?}", base))?;
+    let merge_base = git(dir, &["merge-base", commit.trim(), "HEAD"]).await?;
+
+    let changed = git(
+        dir,
+        &["diff", "--relative", "--name-only", "-z", "--diff-filter=d", merge_base.trim()],
+    )
+    .await?;
+    let untracked = git(dir, &["ls-files", "--others", "--exclude-standard", "-z"]).await?;
+
+    Ok(split_paths(&changed)
+        .chain(split_paths(&untracked))
+        .map(|p| dir.join(p))
+        .collect())
+}
+
+async fn git(dir: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to run git: {}", e))?;
+
+    if !output.status.success() {
+        anyhow::bail!(
+            "git {} failed in {:?}: {}",
+            args.join(" "),
+            dir,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn split_paths(output: &str) -> impl Iterator<Item = &str> {
+    output.split('\0').filter(|p| !p.is_empty())
+}
+
+impl ProjectIndexer {
+    /// Resolves `scope` in every root's repository into a search scope over
+    /// the matching files. Roots outside a git repository are skipped; it is
+    /// an error only when no root could be resolved.
+    pub async fn git_scope(&self, scope: &GitScope) -> anyhow::Result<SearchScope> {
+        let mut files = HashSet::new();
+        let mut last_error = None;
+        let mut resolved = false;
+
+        for root in self.roots().await {
+            let result = match scope {
+                GitScope::Tracked => tracked_files(&root.path).await,
+                GitScope::Staged => staged_files(&root.path).await,
+                GitScope::Changed { base } => changed_files(&root.path, base).await,
+            };
+            match result {
+                Ok(root_files) => {
+                    resolved = true;
+                    files.extend(root_files);
+                }
+                Err(e) => {
+                    eprintln!("Error reading git state of root {}: {}", root.name, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match (resolved, last_error) {
+            (false, Some(e)) => Err(e),
+            _ => Ok(SearchScope::Files(Arc::new(files))),
+        }
+    }
+}
@@ -1,16 +1,35 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
 use tokio::fs;
 use tokio::sync::RwLock;
 
+use crate::git;
+use crate::language::LanguageRegistry;
+use crate::ranking::{self, Bm25, TermStats};
+use crate::retrieval::{self, IndexedChunk};
+use crate::tasks::DEFAULT_TASK_TAGS;
+use crate::workspace::{IgnoreRules, SearchScope, WorkspaceRoot};
+
 #[derive(Debug, Clone)]
 pub struct IndexedFile {
     pub path: PathBuf,
+    /// Name of the workspace root that owns this file, if any.
+    pub root: Option<String>,
+    /// Path relative to `root`, or the full path for files outside every root.
+    pub relative_path: PathBuf,
     pub content: String,
     pub language: String,
     pub symbols: Vec<Symbol>,
 }
 
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub file: IndexedFile,
+    pub score: f64,
+}
+
 #[derive(Debug, Clone)]
 pub struct Symbol {
     pub name: String,
@@ -19,7 +38,8 @@ pub struct Symbol {
     pub column: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SymbolKind {
     Function,
     Class,
@@ -29,44 +49,259 @@ pub enum SymbolKind {
 }
 
 pub struct ProjectIndexer {
-    files: RwLock<HashMap<PathBuf, IndexedFile>>,
-    #[allow(dead_code)] // Se guardará para uso futuro (ej: búsquedas relativas al workspace)
-    workspace_path: PathBuf,
+    pub(crate) files: RwLock<HashMap<PathBuf, IndexedFile>>,
+    pub(crate) terms: RwLock<HashMap<PathBuf, TermStats>>,
+    pub(crate) chunks: RwLock<HashMap<PathBuf, Vec<IndexedChunk>>>,
+    /// How long each file took to read, parse and index.
+    pub(crate) index_times: RwLock<HashMap<PathBuf, Duration>>,
+    roots: RwLock<Vec<WorkspaceRoot>>,
+    languages: LanguageRegistry,
+    pub(crate) task_tags: RwLock<Vec<String>>,
 }
 
 impl ProjectIndexer {
     pub fn new(workspace_path: PathBuf) -> Self {
+        Self::with_languages(workspace_path, LanguageRegistry::builtin())
+    }
+
+    pub fn with_languages(workspace_path: PathBuf, languages: LanguageRegistry) -> Self {
+        let root = WorkspaceRoot {
+            name: root_name(&workspace_path),
+            path: workspace_path,
+            ignore: IgnoreRules::default(),
+        };
+
         Self {
             files: RwLock::new(HashMap::new()),
-            workspace_path,
+            terms: RwLock::new(HashMap::new()),
+            chunks: RwLock::new(HashMap::new()),
+            index_times: RwLock::new(HashMap::new()),
+            roots: RwLock::new(vec![root]),
+            languages,
+            task_tags: RwLock::new(DEFAULT_TASK_TAGS.iter().map(|t| t.to_string()).collect()),
+        }
+    }
+
+    pub fn languages(&self) -> &LanguageRegistry {
+        &self.languages
+    }
+
+    pub async fn roots(&self) -> Vec<WorkspaceRoot> {
+        self.roots.read().await.clone()
+    }
+
+    pub async fn add_root(&self, path: PathBuf) -> String {
+        self.add_root_with_ignore(path, IgnoreRules::default()).await
+    }
+
+    /// Registers a workspace root and returns its name. Adding a path that is
+    /// already a root only replaces its ignore rules. Files already indexed
+    /// under `path` move to the new root.
+    pub async fn add_root_with_ignore(&self, path: PathBuf, ignore: IgnoreRules) -> String {
+        let name = {
+            let mut roots = self.roots.write().await;
+
+            if let Some(existing) = roots.iter_mut().find(|r| r.path == path) {
+                existing.ignore = ignore;
+                return existing.name.clone();
+            }
+
+            let base = root_name(&path);
+            let mut name = base.clone();
+            let mut suffix = 2;
+            while roots.iter().any(|r| r.name == name) {
+                name = format!("{}-{}", base, suffix);
+                suffix += 1;
+            }
+
+            roots.push(WorkspaceRoot {
+                name: name.clone(),
+                path: path.clone(),
+                ignore,
+            });
+
+            name
+        };
+
+        self.reattribute(&path).await;
+        name
+    }
+
+    /// Points every indexed file under `path` at the root that now owns it.
+    async fn reattribute(&self, path: &Path) {
+        let mut files = self.files.write().await;
+        for file in files.values_mut().filter(|f| f.path.starts_with(path)) {
+            let (root, relative_path) = self.locate(&file.path).await;
+            file.root = root;
+            file.relative_path = relative_path;
         }
     }
 
+    /// Removes a root. Files it owned move to the root enclosing it, unless
+    /// that root's ignore rules skip them; all others are dropped.
+    pub async fn remove_root(&self, name: &str) -> bool {
+        let removed = {
+            let mut roots = self.roots.write().await;
+            match roots.iter().position(|r| r.name == name) {
+                Some(index) => roots.remove(index),
+                None => return false,
+            }
+        };
+
+        // Outer walks skip nested roots, so these files are not indexed anywhere else
+        let enclosing = self.owning_root(&removed.path).await;
+        self.files.write().await.retain(|path, file| {
+            file.root.as_deref() != Some(name)
+                || enclosing.as_ref().is_some_and(|root| {
+                    let relative = root.relative_path(path).unwrap_or_default();
+                    !root.ignore.is_ignored_within(&relative)
+                })
+        });
+        self.reattribute(&removed.path).await;
+
+        let files = self.files.read().await;
+        let mut terms = self.terms.write().await;
+        terms.retain(|path, _| files.contains_key(path));
+        let mut chunks = self.chunks.write().await;
+        chunks.retain(|path, _| files.contains_key(path));
+        let mut index_times = self.index_times.write().await;
+        index_times.retain(|path, _| files.contains_key(path));
+
+        true
+    }
+
+    pub async fn index_root(&self, name: &str) -> anyhow::Result<()> {
+        let root = self.root(name).await?;
+        self.index_tree(&root.path, &root.path, &root.ignore).await
+    }
+
+    pub async fn index_all(&self) -> anyhow::Result<()> {
+        for root in self.roots().await {
+            if let Err(e) = self.index_root(&root.name).await {
+                eprintln!("Error indexing root {}: {}", root.name, e);
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn index_file(&self, path: PathBuf) -> anyhow::Result<()> {
+        let started = Instant::now();
         let content = fs::read_to_string(&path).await?;
-        let language = self.detect_language(&path);
+        let language = self.detect_language(&path, &content);
         let symbols = self.extract_symbols(&content, &language);
+        self.store_file(path, content, language, symbols, Some(started)).await;
+        Ok(())
+    }
+
+    /// Adds a file with already-known symbols to every index, replacing any
+    /// previous entry for `path`. `started` is when indexing the file began,
+    /// for files that were indexed rather than imported.
+    pub(crate) async fn store_file(
+        &self,
+        path: PathBuf,
+        content: String,
+        language: String,
+        symbols: Vec<Symbol>,
+        started: Option<Instant>,
+    ) {
+        let (root, relative_path) = self.locate(&path).await;
+        let term_stats = TermStats::from_text(&content);
 
         let indexed = IndexedFile {
             path: path.clone(),
+            root,
+            relative_path,
             content,
             language,
             symbols,
         };
+        let file_chunks = retrieval::chunk_file(&indexed, self.languages.get(&indexed.language));
 
         let mut files = self.files.write().await;
+        let mut terms = self.terms.write().await;
+        let mut chunks = self.chunks.write().await;
+        let mut index_times = self.index_times.write().await;
+        terms.insert(path.clone(), term_stats);
+        chunks.insert(path.clone(), file_chunks);
+        match started {
+            Some(started) => index_times.insert(path.clone(), started.elapsed()),
+            None => index_times.remove(&path),
+        };
         files.insert(path, indexed);
-
-        Ok(())
     }
 
+    /// Indexes `dir` using the ignore rules of the root that contains it, or
+    /// the default rules when it lies outside every root. Roots nested inside
+    /// `dir` are indexed with their own rules.
     pub async fn index_directory(&self, dir: &Path) -> anyhow::Result<()> {
-        self.index_directory_recursive(dir).await
+        let owner = self.owning_root(dir).await;
+        let result = match owner {
+            Some(root) => self.index_tree(dir, &root.path, &root.ignore).await,
+            None => self.index_tree(dir, dir, &IgnoreRules::default()).await,
+        };
+
+        for nested in self.nested_roots(dir).await {
+            if let Err(e) = self.index_tree(&nested.path, &nested.path, &nested.ignore).await {
+                eprintln!("Error indexing root {}: {}", nested.name, e);
+            }
+        }
+
+        result
+    }
+
+    /// Walks `dir`, or indexes only its git-tracked files when the rules ask
+    /// for it and `dir` is inside a repository. Roots nested inside `dir` are
+    /// skipped; they are indexed on their own, with their own rules.
+    async fn index_tree(&self, dir: &Path, base: &Path, ignore: &IgnoreRules) -> anyhow::Result<()> {
+        let nested: Vec<PathBuf> = self.nested_roots(dir).await.into_iter().map(|r| r.path).collect();
+
+        if ignore.tracked_only {
+            match git::tracked_files(dir).await {
+                Ok(tracked) => {
+                    self.index_tracked(tracked, base, ignore, &nested).await;
+                    return Ok(());
+                }
+                Err(e) => eprintln!("Error listing tracked files in {:?}, indexing all files: {}", dir, e),
+            }
+        }
+
+        self.index_directory_recursive(dir, base, ignore, &nested).await
+    }
+
+    async fn index_tracked(&self, tracked: HashSet<PathBuf>, base: &Path, ignore: &IgnoreRules, nested: &[PathBuf]) {
+        let mut tracked: Vec<PathBuf> = tracked.into_iter().collect();
+        tracked.sort();
+
+        for path in tracked {
+            if nested.iter().any(|n| path.starts_with(n)) {
+                continue;
+            }
+
+            // The walk skips ignored directories; here every ancestor is checked instead
+            let relative = path.strip_prefix(base).unwrap_or(&path);
+            if ignore.is_ignored_within(relative) {
+                continue;
+            }
+
+            // Deleted files and submodules are still listed by git
+            if !path.is_file() {
+                continue;
+            }
+
+            let path_clone = path.clone();
+            if let Err(e) = self.index_file(path).await {
+                eprintln!("Error indexing file {:?}: {}", path_clone, e);
+            }
+        }
     }
 
     fn index_directory_recursive<'a>(
         &'a self,
         dir: &'a Path,
+        base: &'a Path,
+        ignore: &'a IgnoreRules,
+        nested: &'a [PathBuf],
     ) -> std::pin::Pin<Box<dyn std::future::Future<Output = anyhow::Result<()>> + Send + 'a>> {
         Box::pin(async move {
             let mut entries = fs::read_dir(dir).await?;
@@ -76,23 +311,15 @@ impl ProjectIndexer {
             while let Some(entry) = entries.next_entry().await? {
                 let path = entry.path();
 
-                // Skip hidden files and common ignore patterns
-                if path.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|s| s.starts_with('.'))
-                    .unwrap_or(false)
-                {
+                let relative = path.strip_prefix(base).unwrap_or(&path);
+                if ignore.is_ignored(relative) {
                     continue;
                 }
 
                 if path.is_dir() {
-                    // Skip common directories
-                    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-                    if matches!(name, "node_modules" | "target" | ".git" | "dist" | "build") {
-                        continue;
+                    if !nested.contains(&path) {
+                        subdirs.push(path);
                     }
-
-                    subdirs.push(path);
                 } else if path.is_file() {
                     let path_clone = path.clone();
                     if let Err(e) = self.index_file(path).await {
@@ -103,7 +330,7 @@ impl ProjectIndexer {
 
             // Second pass: recursively index subdirectories
             for subdir in subdirs {
-                if let Err(e) = self.index_directory_recursive(&subdir).await {
+                if let Err(e) = self.index_directory_recursive(&subdir, base, ignore, nested).await {
                     eprintln!("Error indexing directory {:?}: {}", subdir, e);
                 }
             }
@@ -112,29 +339,81 @@ impl ProjectIndexer {
         })
     }
 
+    /// Files matching `query`, most relevant first.
     pub async fn search(&self, query: &str) -> Vec<IndexedFile> {
+        self.search_in(query, &SearchScope::All).await
+    }
+
+    pub async fn search_in(&self, query: &str, scope: &SearchScope) -> Vec<IndexedFile> {
+        self.search_ranked(query, scope)
+            .await
+            .into_iter()
+            .map(|hit| hit.file)
+            .collect()
+    }
+
+    /// Files whose path or content contains `query`, ranked by BM25 over the
+    /// query terms plus boosts for path matches and symbol definitions.
+    pub async fn search_ranked(&self, query: &str, scope: &SearchScope) -> Vec<SearchHit> {
         let files = self.files.read().await;
+        let terms = self.terms.read().await;
         let query_lower = query.to_lowercase();
 
-        files
+        let mut query_terms = ranking::tokenize(query);
+        query_terms.sort();
+        query_terms.dedup();
+
+        let bm25 = Bm25::new(terms.values());
+        let weights = bm25.weights(&query_terms);
+
+        let mut hits: Vec<SearchHit> = files
             .values()
+            .filter(|file| scope.includes(file))
             .filter(|file| {
-                file.path
+                file.relative_path
                     .to_string_lossy()
                     .to_lowercase()
                     .contains(&query_lower)
                     || file.content.to_lowercase().contains(&query_lower)
             })
+            .map(|file| SearchHit {
+                score: terms
+                    .get(&file.path)
+                    .map(|stats| bm25.score(stats, &weights))
+                    .unwrap_or(0.0)
+                    + ranking::boost(file, &query_lower, &query_terms),
+                file: file.clone(),
+            })
+            .collect();
+
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.file.relative_path.cmp(&b.file.relative_path))
+        });
+        hits
+    }
+
+    /// Every indexed file in `scope`, unranked.
+    pub async fn files_in(&self, scope: &SearchScope) -> Vec<IndexedFile> {
+        let files = self.files.read().await;
+        files
+            .values()
+            .filter(|file| scope.includes(file))
             .cloned()
             .collect()
     }
 
     pub async fn find_symbols(&self, name: &str) -> Vec<(IndexedFile, Symbol)> {
+        self.find_symbols_in(name, &SearchScope::All).await
+    }
+
+    pub async fn find_symbols_in(&self, name: &str, scope: &SearchScope) -> Vec<(IndexedFile, Symbol)> {
         let files = self.files.read().await;
         let mut results = Vec::new();
         let name_lower = name.to_lowercase();
 
-        for file in files.values() {
+        for file in files.values().filter(|f| scope.includes(f)) {
             for symbol in &file.symbols {
                 if symbol.name.to_lowercase().contains(&name_lower) {
                     results.push((file.clone(), symbol.clone()));
@@ -145,133 +424,78 @@ impl ProjectIndexer {
         results
     }
 
-    fn detect_language(&self, path: &Path) -> String {
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-
-        match ext.as_str() {
-            "ts" | "tsx" => "typescript",
-            "js" | "jsx" => "javascript",
-            "rs" => "rust",
-            "py" => "python",
-            "java" => "java",
-            "go" => "go",
-            "cpp" | "cc" | "cxx" => "cpp",
-            "c" => "c",
-            _ => "plaintext",
+    pub(crate) async fn root(&self, name: &str) -> anyhow::Result<WorkspaceRoot> {
+        self.roots
+            .read()
+            .await
+            .iter()
+            .find(|r| r.name == name)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Workspace root {} not found", name))
+    }
+
+    /// Roots strictly inside `dir`.
+    async fn nested_roots(&self, dir: &Path) -> Vec<WorkspaceRoot> {
+        let roots = self.roots.read().await;
+        roots
+            .iter()
+            .filter(|r| r.path != dir && r.path.starts_with(dir))
+            .cloned()
+            .collect()
+    }
+
+    /// Innermost root containing `path`, so nested roots take precedence.
+    async fn owning_root(&self, path: &Path) -> Option<WorkspaceRoot> {
+        let roots = self.roots.read().await;
+        roots
+            .iter()
+            .filter(|r| r.contains(path))
+            .max_by_key(|r| r.path.components().count())
+            .cloned()
+    }
+
+    async fn locate(&self, path: &Path) -> (Option<String>, PathBuf) {
+        match self.owning_root(path).await {
+            Some(root) => {
+                let relative = root.relative_path(path).unwrap_or_else(|| path.to_path_buf());
+                (Some(root.name), relative)
+            }
+            None => (None, path.to_path_buf()),
         }
-        .to_string()
+    }
+
+    pub(crate) fn detect_language(&self, path: &Path, content: &str) -> String {
+        self.languages.detect(path, Some(content)).to_string()
     }
 
     fn extract_symbols(&self, content: &str, language: &str) -> Vec<Symbol> {
         let mut symbols = Vec::new();
+        let rules = match self.languages.get(language) {
+            Some(definition) if !definition.symbols.is_empty() => &definition.symbols,
+            _ => return symbols,
+        };
 
         for (line_idx, line) in content.lines().enumerate() {
             let line_num = (line_idx + 1) as u32;
 
-            match language {
-                "typescript" | "javascript" => {
-                    // Extract functions
-                    if let Some(pos) = line.find("function ") {
-                        if let Some(name_start) = line[pos + 9..].find(|c: char| c.is_alphanumeric() || c == '_') {
-                            let name = line[pos + 9 + name_start..]
-                                .split(|c: char| !c.is_alphanumeric() && c != '_')
-                                .next()
-                                .unwrap_or("");
-                            if !name.is_empty() {
-                                symbols.push(Symbol {
-                                    name: name.to_string(),
-                                    kind: SymbolKind::Function,
-                                    line: line_num,
-                                    column: (pos + 9 + name_start) as u32,
-                                });
-                            }
-                        }
-                    }
-
-                    // Extract classes
-                    if let Some(pos) = line.find("class ") {
-                        if let Some(name_start) = line[pos + 6..].find(|c: char| c.is_alphanumeric() || c == '_') {
-                            let name = line[pos + 6 + name_start..]
-                                .split(|c: char| !c.is_alphanumeric() && c != '_')
-                                .next()
-                                .unwrap_or("");
-                            if !name.is_empty() {
-                                symbols.push(Symbol {
-                                    name: name.to_string(),
-                                    kind: SymbolKind::Class,
-                                    line: line_num,
-                                    column: (pos + 6 + name_start) as u32,
-                                });
-                            }
-                        }
-                    }
-
-                    // Extract const/let/var
-                    for keyword in &["const ", "let ", "var "] {
-                        if let Some(pos) = line.find(keyword) {
-                            if let Some(name_start) = line[pos + keyword.len()..].find(|c: char| c.is_alphanumeric() || c == '_') {
-                                let name = line[pos + keyword.len() + name_start..]
-                                    .split(|c: char| !c.is_alphanumeric() && c != '_')
-                                    .next()
-                                    .unwrap_or("");
-                                if !name.is_empty() {
-                                    symbols.push(Symbol {
-                                        name: name.to_string(),
-                                        kind: if keyword == &"const " {
-                                            SymbolKind::Constant
-                                        } else {
-                                            SymbolKind::Variable
-                                        },
-                                        line: line_num,
-                                        column: (pos + keyword.len() + name_start) as u32,
-                                    });
-                                }
-                            }
+            for rule in rules {
+                let keyword = rule.keyword.as_str();
+                if let Some(pos) = line.find(keyword) {
+                    if let Some(name_start) = line[pos + keyword.len()..].find(|c: char| c.is_alphanumeric() || c == '_') {
+                        let name = line[pos + keyword.len() + name_start..]
+                            .split(|c: char| !c.is_alphanumeric() && c != '_')
+                            .next()
+                            .unwrap_or("");
+                        if !name.is_empty() {
+                            symbols.push(Symbol {
+                                name: name.to_string(),
+                                kind: rule.kind.clone(),
+                                line: line_num,
+                                column: (pos + keyword.len() + name_start) as u32,
+                            });
                         }
                     }
                 }
-                "rust" => {
-                    // Extract functions
-                    if let Some(pos) = line.find("fn ") {
-                        if let Some(name_start) = line[pos + 3..].find(|c: char| c.is_alphanumeric() || c == '_') {
-                            let name = line[pos + 3 + name_start..]
-                                .split(|c: char| !c.is_alphanumeric() && c != '_')
-                                .next()
-                                .unwrap_or("");
-                            if !name.is_empty() {
-                                symbols.push(Symbol {
-                                    name: name.to_string(),
-                                    kind: SymbolKind::Function,
-                                    line: line_num,
-                                    column: (pos + 3 + name_start) as u32,
-                                });
-                            }
-                        }
-                    }
-
-                    // Extract structs
-                    if let Some(pos) = line.find("struct ") {
-                        if let Some(name_start) = line[pos + 7..].find(|c: char| c.is_alphanumeric() || c == '_') {
-                            let name = line[pos + 7 + name_start..]
-                                .split(|c: char| !c.is_alphanumeric() && c != '_')
-                                .next()
-                                .unwrap_or("");
-                            if !name.is_empty() {
-                                symbols.push(Symbol {
-                                    name: name.to_string(),
-                                    kind: SymbolKind::Class,
-                                    line: line_num,
-                                    column: (pos + 7 + name_start) as u32,
-                                });
-                            }
-                        }
-                    }
-                }
-                _ => {}
             }
         }
 
@@ -279,3 +503,9 @@ impl ProjectIndexer {
     }
 }
 
+fn root_name(path: &Path) -> String {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.to_string())
+        .unwrap_or_else(|| path.to_string_lossy().to_string())
+}
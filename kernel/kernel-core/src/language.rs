@@ -0,0 +1,189 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::indexer::SymbolKind;
+
+/// Built-in language definitions, in the same format accepted by `LanguageRegistry::load`.
+const BUILTIN_LANGUAGES: &str = include_str!("languages.json");
+
+/// Language ID returned when nothing in the registry matches.
+pub const PLAINTEXT: &str = "plaintext";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlockComment {
+    pub start: String,
+    pub end: String,
+}
+
+/// A keyword that introduces a symbol definition, e.g. `fn ` for Rust functions.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolRule {
+    pub keyword: String,
+    pub kind: SymbolKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageDefinition {
+    pub id: String,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    /// Exact file names such as `Dockerfile` or `Makefile`.
+    #[serde(default)]
+    pub file_names: Vec<String>,
+    /// Interpreter names matched against the `#!` line, e.g. `python` matches
+    /// `#!/usr/bin/env python3`.
+    #[serde(default)]
+    pub shebangs: Vec<String>,
+    #[serde(default)]
+    pub line_comments: Vec<String>,
+    #[serde(default)]
+    pub block_comment: Option<BlockComment>,
+    /// String delimiters, used to tell comment markers inside strings apart.
+    #[serde(default = "default_strings")]
+    pub strings: Vec<String>,
+    #[serde(default)]
+    pub symbols: Vec<SymbolRule>,
+}
+
+fn default_strings() -> Vec<String> {
+    vec!["\"".to_string()]
+}
+
+#[derive(Debug, Deserialize)]
+struct RegistryFile {
+    languages: Vec<LanguageDefinition>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LanguageRegistry {
+    languages: Vec<LanguageDefinition>,
+}
+
+impl LanguageRegistry {
+    /// Registry with no languages; everything is detected as plaintext.
+    pub fn empty() -> Self {
+        Self {
+            languages: Vec::new(),
+        }
+    }
+
+    pub fn builtin() -> Self {
+        Self::from_json(BUILTIN_LANGUAGES).expect("built-in languages.json is valid")
+    }
+
+    pub fn from_json(json: &str) -> anyhow::Result<Self> {
+        let file: RegistryFile = serde_json::from_str(json)?;
+        let mut registry = Self::empty();
+        for language in file.languages {
+            registry.register(language);
+        }
+        Ok(registry)
+    }
+
+    /// Loads the built-in languages and applies the definitions in `path` on top.
+    /// A definition with an existing ID replaces the built-in one.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        let overrides = Self::from_json(&json)?;
+        let mut registry = Self::builtin();
+        for language in overrides.languages {
+            registry.register(language);
+        }
+        Ok(registry)
+    }
+
+    /// Like `load`, but falls back to the built-in languages when the file is
+    /// missing or invalid.
+    pub fn load_or_builtin(path: &Path) -> Self {
+        if !path.exists() {
+            return Self::builtin();
+        }
+        match Self::load(path) {
+            Ok(registry) => registry,
+            Err(e) => {
+                eprintln!("Error loading language registry {:?}: {}", path, e);
+                Self::builtin()
+            }
+        }
+    }
+
+    pub fn register(&mut self, language: LanguageDefinition) {
+        match self.languages.iter_mut().find(|l| l.id == language.id) {
+            Some(existing) => *existing = language,
+            None => self.languages.push(language),
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&LanguageDefinition> {
+        self.languages.iter().find(|l| l.id == id)
+    }
+
+    pub fn languages(&self) -> &[LanguageDefinition] {
+        &self.languages
+    }
+
+    /// Detects the language of `path`. File names win over extensions; the
+    /// shebang in `content` is only consulted when neither matches.
+    pub fn detect(&self, path: &Path, content: Option<&str>) -> &str {
+        self.detect_by_path(path)
+            .or_else(|| content.and_then(|c| self.detect_by_shebang(c)))
+            .unwrap_or(PLAINTEXT)
+    }
+
+    pub fn detect_by_path(&self, path: &Path) -> Option<&str> {
+        let file_name = path.file_name().and_then(|n| n.to_str())?;
+
+        if let Some(language) = self
+            .languages
+            .iter()
+            .find(|l| l.file_names.iter().any(|n| n == file_name))
+        {
+            return Some(&language.id);
+        }
+
+        let ext = path.extension().and_then(|e| e.to_str())?.to_lowercase();
+        self.languages
+            .iter()
+            .find(|l| l.extensions.iter().any(|e| e.eq_ignore_ascii_case(&ext)))
+            .map(|l| l.id.as_str())
+    }
+
+    pub fn detect_by_shebang(&self, content: &str) -> Option<&str> {
+        let interpreter = shebang_interpreter(content.lines().next()?)?;
+        self.languages
+            .iter()
+            .find(|l| {
+                l.shebangs
+                    .iter()
+                    .any(|s| interpreter_matches(interpreter, s))
+            })
+            .map(|l| l.id.as_str())
+    }
+}
+
+impl Default for LanguageRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+/// Extracts the interpreter name from a `#!` line, looking through `env`.
+fn shebang_interpreter(line: &str) -> Option<&str> {
+    let mut parts = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = parts.next()?.rsplit('/').next()?;
+
+    if program == "env" {
+        program = parts.find(|p| !p.starts_with('-') && !p.contains('='))?;
+    }
+
+    Some(program)
+}
+
+/// `python` matches `python`, `python3` and `python3.12`, but not `pythonista`.
+fn interpreter_matches(interpreter: &str, pattern: &str) -> bool {
+    interpreter
+        .strip_prefix(pattern)
+        .map(|rest| rest.chars().all(|c| c.is_ascii_digit() || c == '.'))
+        .unwrap_or(false)
+}
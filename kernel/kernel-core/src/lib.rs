@@ -1,7 +1,33 @@
 pub mod server;
+pub mod comments;
+pub mod duplicates;
+mod fsutil;
+pub mod git;
 pub mod indexer;
+pub mod language;
 pub mod license;
+pub mod machine;
+pub mod metrics;
+pub mod query;
+pub mod ranking;
+pub mod replace;
+pub mod retrieval;
+pub mod scip;
+pub mod stats;
+pub mod tags;
+pub mod tasks;
+pub mod workspace;
 
 pub use server::{KernelCore, KernelRequest, KernelResponse};
-pub use indexer::{ProjectIndexer, IndexedFile, Symbol, SymbolKind};
-pub use license::{LicenseManager, LicenseInfo, Feature};
+pub use duplicates::{CloneGroup, CloneLocation, DuplicateConfig};
+pub use git::GitScope;
+pub use indexer::{ProjectIndexer, IndexedFile, SearchHit, Symbol, SymbolKind};
+pub use language::{LanguageRegistry, LanguageDefinition};
+pub use license::{Clock, ExpiryState, SystemClock, LicenseManager, LicenseInfo, LicenseError, LicenseStatus, LicensePayload, LicenseTier, SignedLicense, Feature, FeatureNotLicensed, Entitlement, Quota, QuotaExceeded, QuotaUsage, SeatRelease, SeatToken, SignedSeatRelease, SignedSeatToken, RevocationList, SignedRevocationList};
+pub use metrics::{DirectoryMetrics, FileMetrics, SymbolMetrics};
+pub use query::{Query, QueryError, QueryHit};
+pub use replace::{ReplaceRequest, ReplacePreview, FilePreview, ReplaceEdit, ReplaceOutcome};
+pub use retrieval::{CodeChunk, ChunkHit};
+pub use stats::{FileStats, IndexStats, LanguageStats};
+pub use tasks::{TaskComment, TaskFilter};
+pub use workspace::{IgnoreRules, SearchScope, WorkspaceRoot};
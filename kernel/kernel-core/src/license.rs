@@ -1,93 +1,1144 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use hmac::{Hmac, Mac};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::fsutil::write_atomic;
+use crate::machine::machine_fingerprint;
+
+/// Public half of the key that signs release license files.
+const LICENSE_PUBLIC_KEY: [u8; 32] = [
+    40, 62, 19, 111, 155, 176, 91, 190, 220, 175, 65, 229, 158, 133, 1, 131, 23, 106, 96, 160, 143, 83, 1,
+    233, 49, 108, 184, 90, 214, 2, 193, 60,
+];
+
+const FREE_FEATURES: &[Feature] = &[
+    Feature::BasicEditor,
+    Feature::FileExplorer,
+    Feature::Terminal,
+    Feature::BasicLsp,
+];
+
+/// Days a license keeps working after `expires_at` before falling back to free.
+pub const DEFAULT_GRACE_PERIOD_DAYS: u64 = 7;
+
+pub const DEFAULT_TRIAL_DAYS: u64 = 14;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Clock skew tolerated before a trial counts as tampered with.
+const TRIAL_CLOCK_TOLERANCE: u64 = 24 * 60 * 60;
+
+/// Mixed into the MAC key of the trial and usage records, which is bound to
+/// the machine fingerprint. The records only need to be tamper-evident: edits
+/// and copies are detected, a marker kept elsewhere remembers the trial when
+/// its record is deleted, and deleting the usage record only starts its
+/// counts over.
+const TRIAL_KEY_CONTEXT: &[u8] = b"meacode-studio trial v2";
+
+/// Mixed into the key a seat release is signed with, see `seat_release_key`.
+const SEAT_RELEASE_KEY_CONTEXT: &[u8] = b"meacode-studio seat release v1";
+
+/// Source of the current Unix time in seconds. Closures implement it, so
+/// tests can pass `|| 1_700_000_000`.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> u64;
+}
+
+impl<F: Fn() -> u64 + Send + Sync> Clock for F {
+    fn now(&self) -> u64 {
+        self()
+    }
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpiryState {
+    /// No expiry date.
+    Perpetual,
+    Active,
+    /// Past `expires_at` but within the grace period; features still work.
+    Grace,
+    /// Past the grace period; only free features are available.
+    Expired,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LicenseInfo {
     pub is_premium: bool,
-    pub features: Vec<String>,
+    /// Features this build knows; unknown names in the license are left out.
+    pub features: Vec<Feature>,
+    /// Limits set by the license. A quota that is not listed is unlimited.
+    #[serde(default)]
+    pub quotas: BTreeMap<Quota, u64>,
     pub expires_at: Option<u64>, // Unix timestamp
+    #[serde(default)]
+    pub license_id: Option<String>,
+    #[serde(default)]
+    pub licensee: Option<String>,
+    #[serde(default)]
+    pub tier: LicenseTier,
+    /// Set when an expired trial or license has fallen back to the free tier.
+    #[serde(default)]
+    pub downgraded_from: Option<LicenseTier>,
+    /// Seats bought with a team license.
+    #[serde(default)]
+    pub seats: Option<u32>,
+    /// Seat this machine occupies, from 1 to `seats`.
+    #[serde(default)]
+    pub seat: Option<u32>,
+    /// Set when the license was downgraded because its ID is on a
+    /// revocation list; `license_id` and `downgraded_from` name it.
+    #[serde(default)]
+    pub revoked: bool,
+}
+
+/// License state as shown to the user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LicenseStatus {
+    pub license: LicenseInfo,
+    pub state: ExpiryState,
+    /// Premium access after expiry is taken into account.
+    pub is_premium: bool,
+    pub remaining_days: Option<i64>,
+    pub grace_days_remaining: Option<i64>,
+    /// One entry per quota the license limits.
+    pub quotas: Vec<QuotaUsage>,
+}
+
+/// Declares an enum whose variants are written in license files by name.
+/// The name is given once and used for serde, `as_str` and `ALL`, so the
+/// two can never disagree.
+macro_rules! named_enum {
+    ($(#[$meta:meta])* pub enum $name:ident { $($(#[$variant_meta:meta])* $variant:ident => $wire:literal,)* }) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+        pub enum $name {
+            $($(#[$variant_meta])* #[serde(rename = $wire)] $variant,)*
+        }
+
+        impl $name {
+            pub const ALL: &'static [$name] = &[$($name::$variant,)*];
+
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $($name::$variant => $wire,)*
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+named_enum! {
+    pub enum Feature {
+        BasicEditor => "basic_editor",
+        FileExplorer => "file_explorer",
+        Terminal => "terminal",
+        BasicLsp => "basic_lsp",
+        AICompletion => "ai_completion",
+        AIChat => "ai_chat",
+        AdvancedRefactoring => "advanced_refactoring",
+        GPUAcceleration => "gpu_acceleration",
+        CustomThemes => "custom_themes",
+        PluginSystem => "plugin_system",
+        CloudSync => "cloud_sync",
+    }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Feature {
-    AICompletion,
-    AIChat,
-    AdvancedRefactoring,
-    GPUAcceleration,
-    CustomThemes,
-    PluginSystem,
-    CloudSync,
+named_enum! {
+    /// Numeric limits a license can set.
+    pub enum Quota {
+        /// AI chat and completion requests per UTC day.
+        AiRequestsPerDay => "ai_requests_per_day",
+        /// Plugins installed at the same time.
+        MaxPlugins => "max_plugins",
+    }
+}
+
+impl Quota {
+    /// Length in seconds of the window after which usage resets, or `None`
+    /// for limits on something the caller counts, like installed plugins.
+    pub fn period(&self) -> Option<u64> {
+        match self {
+            Quota::AiRequestsPerDay => Some(SECONDS_PER_DAY),
+            Quota::MaxPlugins => None,
+        }
+    }
+}
+
+/// A feature or quota name in a license file. Names this build does not
+/// know are kept as written, so licenses issued for newer versions still
+/// load and their digest still matches.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Entitlement<T> {
+    Known(T),
+    Unknown(String),
+}
+
+impl<T> Entitlement<T> {
+    pub fn known(&self) -> Option<&T> {
+        match self {
+            Entitlement::Known(value) => Some(value),
+            Entitlement::Unknown(_) => None,
+        }
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Entitlement<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Entitlement::Known(value) => value.fmt(f),
+            Entitlement::Unknown(name) => f.write_str(name),
+        }
+    }
+}
+
+impl<T> From<T> for Entitlement<T> {
+    fn from(value: T) -> Self {
+        Entitlement::Known(value)
+    }
 }
 
+/// How much of a quota has been used, for the UI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuotaUsage {
+    pub quota: Quota,
+    pub limit: u64,
+    /// Uses counted in the current window; `None` for quotas without one.
+    pub used: Option<u64>,
+    /// Unix time the current window ends.
+    pub resets_at: Option<u64>,
+}
+
+/// Returned at command and kernel boundaries when the license in effect
+/// does not include the feature a request needs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename = "feature_not_licensed")]
+pub struct FeatureNotLicensed {
+    pub feature: Feature,
+    pub tier: LicenseTier,
+}
+
+impl fmt::Display for FeatureNotLicensed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Feature {} is not included in the current license", self.feature.as_str())
+    }
+}
+
+impl std::error::Error for FeatureNotLicensed {}
+
+/// Returned when a request would go over a quota of the license in effect.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename = "quota_exceeded")]
+pub struct QuotaExceeded {
+    pub quota: Quota,
+    pub limit: u64,
+    pub resets_at: Option<u64>,
+}
+
+impl fmt::Display for QuotaExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Quota {} of {} has been reached", self.quota, self.limit)
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LicenseTier {
+    #[default]
+    Free,
+    Trial,
+    Premium,
+}
+
+/// The signed part of a license file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LicensePayload {
+    pub license_id: String,
+    pub licensee: String,
+    pub tier: LicenseTier,
+    pub features: Vec<Entitlement<Feature>>,
+    /// Left out of the file when empty so licenses without quotas keep
+    /// their original shape.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub quotas: BTreeMap<Entitlement<Quota>, u64>,
+    pub issued_at: u64,
+    pub expires_at: Option<u64>,
+    /// Set for team licenses, which are activated one seat per machine.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seats: Option<u32>,
+    /// Hex SHA-256 of the feature list, see `features_digest`. Lets a failed
+    /// signature check tell an edited feature list apart from a bad signature.
+    pub features_digest: String,
+}
+
+/// Grants one seat of a team license to one machine. Seat tokens are issued
+/// by whoever sells the licenses, who keeps track of which machine holds each
+/// seat, so a seat cannot be taken without one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeatToken {
+    pub license_id: String,
+    pub seat: u32,
+    /// Fingerprint of the machine the seat is granted to, as reported by
+    /// `LicenseManager::fingerprint`.
+    pub fingerprint: String,
+    pub issued_at: u64,
+}
+
+/// A seat token file, signed with the same key as license files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedSeatToken {
+    pub token: SeatToken,
+    pub signature: String,
+}
+
+/// Notice from `LicenseManager::release_seat` that a machine gave up its
+/// seat, to send along when asking for a seat token for another machine.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SeatRelease {
+    pub license_id: String,
+    pub seat: u32,
+    /// Fingerprint of the machine that held the seat.
+    pub fingerprint: String,
+    pub released_at: u64,
+}
+
+/// A seat release file. It carries the seat token the seat was held with and
+/// is signed with a key derived from that token, so it cannot be edited or
+/// made for a seat without holding its token.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedSeatRelease {
+    pub release: SeatRelease,
+    /// The seat token file, exactly as issued.
+    pub seat_token: Value,
+    pub signature: String,
+}
+
+/// License IDs that must no longer be honored, e.g. because the key leaked.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RevocationList {
+    pub issued_at: u64,
+    pub revoked: Vec<String>,
+}
+
+/// A revocation list file, signed with the same key as license files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedRevocationList {
+    pub list: RevocationList,
+    pub signature: String,
+}
+
+/// A license file: the payload plus a base64 Ed25519 signature over the
+/// canonical JSON of the payload.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignedLicense {
+    pub payload: LicensePayload,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LicenseError {
+    /// Not a license file: invalid JSON, missing fields, or an undecodable signature.
+    Malformed(String),
+    /// The signature does not match the payload or was made with another key.
+    InvalidSignature,
+    /// The feature list no longer matches the signed digest.
+    TamperedFeatures,
+    /// The license file could not be read or written.
+    Io(String),
+    /// A trial cannot start while a trial or license is in effect.
+    LicenseActive,
+    /// This installation has already used its trial.
+    TrialAlreadyUsed,
+    /// The trial record was edited, copied from elsewhere, or the clock was
+    /// turned back.
+    TrialTampered,
+    /// The quota usage record was edited or copied from elsewhere.
+    UsageTampered,
+    /// Team licenses are activated with `activate_seat` and a seat token.
+    SeatRequired,
+    /// The seat number is outside the license's seat count, or the license
+    /// has no seats.
+    InvalidSeat(u32),
+    /// The seat token was issued to another machine.
+    WrongMachine,
+    /// The seat token is malformed, not signed with the license key, or for
+    /// another license.
+    InvalidSeatToken,
+    /// The seat release is malformed, not signed for its seat token, or for
+    /// another seat.
+    InvalidSeatRelease,
+    /// No machine id was found to derive the fingerprint from.
+    UnknownMachine(String),
+    /// There is no team license seat on this machine to release.
+    NoSeatBound,
+    /// The license ID is on a revocation list.
+    Revoked,
+}
+
+impl fmt::Display for LicenseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LicenseError::Malformed(reason) => write!(f, "Malformed license: {}", reason),
+            LicenseError::InvalidSignature => write!(f, "License signature is invalid"),
+            LicenseError::TamperedFeatures => write!(f, "License feature list has been modified"),
+            LicenseError::Io(reason) => write!(f, "License storage error: {}", reason),
+            LicenseError::LicenseActive => write!(f, "A license is already active"),
+            LicenseError::TrialAlreadyUsed => write!(f, "The trial has already been used on this installation"),
+            LicenseError::TrialTampered => write!(f, "The trial record has been tampered with"),
+            LicenseError::UsageTampered => write!(f, "The quota usage record has been tampered with"),
+            LicenseError::SeatRequired => write!(f, "Team licenses must be activated on a seat"),
+            LicenseError::InvalidSeat(seat) => write!(f, "Seat {} is not part of this license", seat),
+            LicenseError::WrongMachine => write!(f, "The license seat is bound to another machine"),
+            LicenseError::InvalidSeatToken => write!(f, "The seat token is invalid"),
+            LicenseError::InvalidSeatRelease => write!(f, "The seat release is invalid"),
+            LicenseError::UnknownMachine(reason) => write!(f, "This machine could not be identified: {}", reason),
+            LicenseError::NoSeatBound => write!(f, "No team license seat is active on this machine"),
+            LicenseError::Revoked => write!(f, "This license has been revoked"),
+        }
+    }
+}
+
+impl std::error::Error for LicenseError {}
+
 pub struct LicenseManager {
     license: LicenseInfo,
+    public_key: VerifyingKey,
+    clock: Arc<dyn Clock>,
+    grace_period_days: u64,
+    storage: Option<PathBuf>,
+    trial_storage: Option<PathBuf>,
+    trial_marker: Option<PathBuf>,
+    trial_days: u64,
+    usage_storage: Option<PathBuf>,
+    usage: BTreeMap<Quota, QuotaCounter>,
+    fingerprint: OnceLock<String>,
+    /// The seat token file of the active team license seat.
+    seat_token: Option<String>,
+    revocation_lists: Vec<PathBuf>,
+    revoked: HashSet<String>,
+}
+
+/// Uses of one quota in the window starting at `period_start`.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+struct QuotaCounter {
+    period_start: u64,
+    used: u64,
+}
+
+/// Quota usage kept on disk, authenticated like the trial record.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UsageRecord {
+    usage: BTreeMap<Quota, QuotaCounter>,
+    mac: String,
+}
+
+/// Trial state kept on disk, authenticated with an HMAC bound to the machine.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrialRecord {
+    started_at: u64,
+    expires_at: u64,
+    /// Latest time the trial was seen, to notice clocks turned back.
+    last_seen: u64,
+    mac: String,
 }
 
 impl LicenseManager {
     pub fn new() -> Self {
         // Default to free tier
+        Self::with_license(free_license())
+    }
+
+    pub fn with_license(license: LicenseInfo) -> Self {
         Self {
-            license: LicenseInfo {
-                is_premium: false,
-                features: vec![
-                    "basic_editor".to_string(),
-                    "file_explorer".to_string(),
-                    "terminal".to_string(),
-                    "basic_lsp".to_string(),
-                ],
-                expires_at: None,
-            },
+            license,
+            public_key: VerifyingKey::from_bytes(&LICENSE_PUBLIC_KEY).expect("embedded license key is valid"),
+            clock: Arc::new(SystemClock),
+            grace_period_days: DEFAULT_GRACE_PERIOD_DAYS,
+            storage: None,
+            trial_storage: None,
+            trial_marker: None,
+            trial_days: DEFAULT_TRIAL_DAYS,
+            usage_storage: None,
+            usage: BTreeMap::new(),
+            fingerprint: OnceLock::new(),
+            seat_token: None,
+            revocation_lists: Vec::new(),
+            revoked: HashSet::new(),
         }
     }
 
-    pub fn with_license(license: LicenseInfo) -> Self {
-        Self { license }
+    /// Uses `fingerprint` instead of deriving one from this machine.
+    pub fn with_fingerprint(mut self, fingerprint: String) -> Self {
+        self.fingerprint = OnceLock::from(fingerprint);
+        self
+    }
+
+    /// Identifies this machine to team licenses, see `machine_fingerprint`.
+    pub fn fingerprint(&self) -> Result<&str, LicenseError> {
+        if let Some(fingerprint) = self.fingerprint.get() {
+            return Ok(fingerprint);
+        }
+        let fingerprint = machine_fingerprint().map_err(|e| LicenseError::UnknownMachine(e.to_string()))?;
+        Ok(self.fingerprint.get_or_init(|| fingerprint))
     }
 
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub fn with_grace_period_days(mut self, days: u64) -> Self {
+        self.grace_period_days = days;
+        self
+    }
+
+    /// Verifies license files against `public_key` instead of the embedded
+    /// release key, e.g. for staging builds.
+    pub fn with_public_key(mut self, public_key: VerifyingKey) -> Self {
+        self.public_key = public_key;
+        self
+    }
+
+    /// False for every premium feature once the license is past its grace period.
     pub fn has_feature(&self, feature: Feature) -> bool {
-        let feature_name = match feature {
-            Feature::AICompletion => "ai_completion",
-            Feature::AIChat => "ai_chat",
-            Feature::AdvancedRefactoring => "advanced_refactoring",
-            Feature::GPUAcceleration => "gpu_acceleration",
-            Feature::CustomThemes => "custom_themes",
-            Feature::PluginSystem => "plugin_system",
-            Feature::CloudSync => "cloud_sync",
-        };
+        if self.expiry_state() == ExpiryState::Expired {
+            return FREE_FEATURES.contains(&feature);
+        }
+        self.license.features.contains(&feature) || self.license.is_premium
+    }
 
-        self.license.features.contains(&feature_name.to_string())
-            || self.license.is_premium
+    /// `has_feature` as a `Result`, for gating requests.
+    pub fn require(&self, feature: Feature) -> Result<(), FeatureNotLicensed> {
+        if self.has_feature(feature) {
+            return Ok(());
+        }
+        Err(FeatureNotLicensed {
+            feature,
+            tier: self.get_license_info().tier,
+        })
     }
 
     pub fn is_premium(&self) -> bool {
-        self.license.is_premium
-    }
-
-    pub fn get_license_info(&self) -> &LicenseInfo {
-        &self.license
-    }
-
-    pub fn activate_license(&mut self, license_key: &str) -> Result<(), String> {
-        // TODO: Implement actual license validation
-        // For now, just a placeholder
-        if license_key.starts_with("PREMIUM-") {
-            self.license.is_premium = true;
-            self.license.features = vec![
-                "basic_editor".to_string(),
-                "file_explorer".to_string(),
-                "terminal".to_string(),
-                "basic_lsp".to_string(),
-                "ai_completion".to_string(),
-                "ai_chat".to_string(),
-                "advanced_refactoring".to_string(),
-                "gpu_acceleration".to_string(),
-                "custom_themes".to_string(),
-                "plugin_system".to_string(),
-                "cloud_sync".to_string(),
-            ];
-            Ok(())
+        self.license.is_premium && self.expiry_state() != ExpiryState::Expired
+    }
+
+    /// Limit of `quota` under the license in effect; `None` means unlimited.
+    pub fn quota(&self, quota: Quota) -> Option<u64> {
+        self.get_license_info().quotas.get(&quota).copied()
+    }
+
+    /// Checks a count the caller keeps, such as installed plugins, against
+    /// the limit of `quota`.
+    pub fn check_quota(&self, quota: Quota, count: u64) -> Result<(), QuotaExceeded> {
+        match self.quota(quota) {
+            Some(limit) if count > limit => Err(QuotaExceeded {
+                quota,
+                limit,
+                resets_at: None,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Counts one use of `quota` in its current window, failing without
+    /// counting once the limit is reached. Usage is saved to the usage file
+    /// so restarting does not reset it. A window later than the clock's
+    /// means the clock was turned back, so it counts as used up rather than
+    /// starting over.
+    pub fn consume(&mut self, quota: Quota) -> Result<(), QuotaExceeded> {
+        let limit = match self.quota(quota) {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+
+        let period_start = self.period_start(quota);
+        let counter = self.usage.entry(quota).or_default();
+        if counter.period_start < period_start {
+            *counter = QuotaCounter {
+                period_start,
+                used: 0,
+            };
+        }
+        if counter.period_start > period_start || counter.used >= limit {
+            return Err(QuotaExceeded {
+                quota,
+                limit,
+                resets_at: self.resets_at(quota),
+            });
+        }
+        counter.used += 1;
+
+        if let Err(e) = self.save_usage() {
+            eprintln!("Error saving quota usage: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Usage of every quota the license in effect limits.
+    pub fn quota_usage(&self) -> Vec<QuotaUsage> {
+        self.get_license_info()
+            .quotas
+            .into_iter()
+            .map(|(quota, limit)| {
+                let used = quota.period().map(|_| {
+                    let period_start = self.period_start(quota);
+                    match self.usage.get(&quota) {
+                        Some(c) if c.period_start > period_start => limit,
+                        Some(c) if c.period_start == period_start => c.used.min(limit),
+                        _ => 0,
+                    }
+                });
+                QuotaUsage {
+                    quota,
+                    limit,
+                    used,
+                    resets_at: self.resets_at(quota),
+                }
+            })
+            .collect()
+    }
+
+    /// Windows are aligned to the Unix epoch, so daily quotas reset at
+    /// midnight UTC. Quotas without a period have a single window.
+    fn period_start(&self, quota: Quota) -> u64 {
+        match quota.period() {
+            Some(period) => {
+                let now = self.clock.now();
+                now - now % period
+            }
+            None => 0,
+        }
+    }
+
+    /// End of the current window, or of a stored window the clock has not
+    /// reached yet.
+    fn resets_at(&self, quota: Quota) -> Option<u64> {
+        let stored = self.usage.get(&quota).map_or(0, |c| c.period_start);
+        quota
+            .period()
+            .map(|period| self.period_start(quota).max(stored).saturating_add(period))
+    }
+
+    /// Marks every quota used up for its current window, for when the stored
+    /// usage cannot be trusted.
+    fn exhaust_usage(&mut self) {
+        for quota in Quota::ALL {
+            let counter = QuotaCounter {
+                period_start: self.period_start(*quota),
+                used: u64::MAX,
+            };
+            self.usage.insert(*quota, counter);
+        }
+    }
+
+    /// Keeps quota usage in `path`; restored by `load`.
+    pub fn with_usage_storage(mut self, path: PathBuf) -> Self {
+        self.usage_storage = Some(path);
+        self
+    }
+
+    fn load_usage(&mut self) -> Result<(), LicenseError> {
+        let path = match &self.usage_storage {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let contents = match read_optional(path)? {
+            Some(contents) => contents,
+            None => return Ok(()),
+        };
+        let record: UsageRecord = serde_json::from_str(&contents).map_err(|_| LicenseError::UsageTampered)?;
+        if record.mac != usage_mac(self.fingerprint()?, &record.usage) {
+            return Err(LicenseError::UsageTampered);
+        }
+        self.usage = record.usage;
+        Ok(())
+    }
+
+    fn save_usage(&self) -> Result<(), LicenseError> {
+        let path = match &self.usage_storage {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        let record = UsageRecord {
+            usage: self.usage.clone(),
+            mac: usage_mac(self.fingerprint()?, &self.usage),
+        };
+        let json = serde_json::to_string(&record).map_err(|e| LicenseError::Io(e.to_string()))?;
+        write_atomic(path, &json).map_err(|e| LicenseError::Io(e.to_string()))
+    }
+
+    /// Trials end exactly at `expires_at`; paid licenses get the grace period.
+    fn grace_period(&self) -> u64 {
+        match self.license.tier {
+            LicenseTier::Trial => 0,
+            _ => self.grace_period_days * SECONDS_PER_DAY,
+        }
+    }
+
+    pub fn expiry_state(&self) -> ExpiryState {
+        let expires_at = match self.license.expires_at {
+            Some(expires_at) => expires_at,
+            None => return ExpiryState::Perpetual,
+        };
+
+        let now = self.clock.now();
+        if now < expires_at {
+            ExpiryState::Active
+        } else if now < expires_at.saturating_add(self.grace_period()) {
+            ExpiryState::Grace
         } else {
-            Err("Invalid license key".to_string())
+            ExpiryState::Expired
+        }
+    }
+
+    /// Whole days until `expires_at`, negative once it has passed; `None`
+    /// for licenses that never expire.
+    pub fn remaining_days(&self) -> Option<i64> {
+        let expires_at = self.license.expires_at?;
+        let remaining = expires_at as i64 - self.clock.now() as i64;
+        Some(remaining.div_euclid(SECONDS_PER_DAY as i64))
+    }
+
+    /// Whole days left before a license in its grace period stops working.
+    pub fn grace_days_remaining(&self) -> Option<i64> {
+        let expires_at = self.license.expires_at?;
+        let cutoff = expires_at.saturating_add(self.grace_period());
+        let remaining = cutoff as i64 - self.clock.now() as i64;
+        Some(remaining.div_euclid(SECONDS_PER_DAY as i64).max(0))
+    }
+
+    /// The license in effect. Once a trial or license is past its grace
+    /// period this is the free tier, with `downgraded_from` naming what ended.
+    pub fn get_license_info(&self) -> LicenseInfo {
+        if self.expiry_state() != ExpiryState::Expired {
+            return self.license.clone();
+        }
+
+        LicenseInfo {
+            expires_at: self.license.expires_at,
+            license_id: self.license.license_id.clone(),
+            licensee: self.license.licensee.clone(),
+            downgraded_from: Some(self.license.tier),
+            ..free_license()
+        }
+    }
+
+    /// Summary for the UI, with expiry applied.
+    pub fn status(&self) -> LicenseStatus {
+        LicenseStatus {
+            license: self.get_license_info(),
+            state: self.expiry_state(),
+            is_premium: self.is_premium(),
+            remaining_days: self.remaining_days(),
+            grace_days_remaining: self.grace_days_remaining(),
+            quotas: self.quota_usage(),
+        }
+    }
+
+    /// Verifies a license file and, if it is genuine, switches to it and
+    /// saves it to the storage file. The current license is kept on any error.
+    pub fn activate_license(&mut self, license_file: &str) -> Result<(), LicenseError> {
+        let payload = self.verify(license_file)?;
+        if payload.seats.is_some() {
+            return Err(LicenseError::SeatRequired);
+        }
+
+        if let Some(path) = &self.storage {
+            write_atomic(path, license_file).map_err(|e| LicenseError::Io(e.to_string()))?;
+        }
+
+        self.apply(payload);
+        Ok(())
+    }
+
+    /// `verify_license` against this manager's key and revocation lists.
+    fn verify(&self, license_file: &str) -> Result<LicensePayload, LicenseError> {
+        let payload = verify_license(license_file, &self.public_key)?;
+        if self.revoked.contains(&payload.license_id) {
+            return Err(LicenseError::Revoked);
+        }
+        Ok(payload)
+    }
+
+    /// Reads a signed revocation list from `path` on every `load`. Lists
+    /// from several places, e.g. one shipped with the app and one in the
+    /// config dir, are combined; a missing file is skipped.
+    pub fn with_revocation_list(mut self, path: PathBuf) -> Self {
+        self.revocation_lists.push(path);
+        self
+    }
+
+    /// Verifies a revocation list and adds its IDs to the ones already
+    /// known. A license in effect that it revokes is downgraded to free.
+    pub fn add_revocation_list(&mut self, list_file: &str) -> Result<(), LicenseError> {
+        let list = verify_revocation_list(list_file, &self.public_key)?;
+        self.revoked.extend(list.revoked);
+        self.enforce_revocation();
+        Ok(())
+    }
+
+    /// Downgrades the license in effect if it has been revoked and returns
+    /// whether it was.
+    fn enforce_revocation(&mut self) -> bool {
+        let revoked = match &self.license.license_id {
+            Some(license_id) => self.revoked.contains(license_id),
+            None => false,
+        };
+        if revoked && !self.license.revoked {
+            self.license = LicenseInfo {
+                license_id: self.license.license_id.take(),
+                licensee: self.license.licensee.take(),
+                downgraded_from: Some(self.license.tier),
+                revoked: true,
+                ..free_license()
+            };
+        }
+        revoked
+    }
+
+    fn load_revocation_lists(&mut self) {
+        for path in self.revocation_lists.clone() {
+            let list_file = match fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => {
+                    eprintln!("Error reading revocation list {:?}: {}", path, e);
+                    continue;
+                }
+            };
+            if let Err(e) = self.add_revocation_list(&list_file) {
+                eprintln!("Error loading revocation list {:?}: {}", path, e);
+            }
+        }
+    }
+
+    /// Activates a seat of a team license on this machine. `seat_token` is a
+    /// signed seat token issued for this machine's fingerprint; it is kept
+    /// next to the license file and checked again on every `load`.
+    ///
+    /// `release` is the seat release of the machine that held the seat
+    /// before, when the seat changes hands. It must be for the same seat and
+    /// from before `seat_token` was issued.
+    pub fn activate_seat(
+        &mut self,
+        license_file: &str,
+        seat_token: &str,
+        release: Option<&str>,
+    ) -> Result<(), LicenseError> {
+        let payload = self.verify(license_file)?;
+        let token = self.check_seat_token(seat_token, &payload)?;
+        if let Some(release) = release {
+            let release = verify_seat_release(release, &self.public_key)?;
+            if release.license_id != token.license_id
+                || release.seat != token.seat
+                || release.released_at > token.issued_at
+            {
+                return Err(LicenseError::InvalidSeatRelease);
+            }
+        }
+
+        if let Some(path) = &self.storage {
+            write_atomic(&seat_path(path), seat_token).map_err(|e| LicenseError::Io(e.to_string()))?;
+            write_atomic(path, license_file).map_err(|e| LicenseError::Io(e.to_string()))?;
+        }
+
+        self.apply(payload);
+        self.license.seat = Some(token.seat);
+        self.seat_token = Some(seat_token.to_string());
+        Ok(())
+    }
+
+    /// Returns `seat_token` if it is signed with the license key, belongs to
+    /// `payload`'s license and names this machine.
+    fn check_seat_token(&self, seat_token: &str, payload: &LicensePayload) -> Result<SeatToken, LicenseError> {
+        let token = verify_seat_token(seat_token, &self.public_key)?;
+        if token.license_id != payload.license_id {
+            return Err(LicenseError::InvalidSeatToken);
+        }
+        match payload.seats {
+            Some(seats) if (1..=seats).contains(&token.seat) => {}
+            _ => return Err(LicenseError::InvalidSeat(token.seat)),
+        }
+        if token.fingerprint != self.fingerprint()? {
+            return Err(LicenseError::WrongMachine);
+        }
+        Ok(token)
+    }
+
+    /// Gives up this machine's team license seat and returns a signed seat
+    /// release, as JSON, to send when asking for a seat token for another
+    /// machine and to pass to `activate_seat` there. Works offline; this
+    /// machine reverts to the free tier.
+    pub fn release_seat(&mut self) -> Result<String, LicenseError> {
+        let (license_id, seat, seat_token) = match (&self.license.license_id, self.license.seat, &self.seat_token) {
+            (Some(license_id), Some(seat), Some(seat_token)) => (license_id.clone(), seat, seat_token.clone()),
+            _ => return Err(LicenseError::NoSeatBound),
+        };
+
+        let release = SeatRelease {
+            license_id,
+            seat,
+            fingerprint: self.fingerprint()?.to_string(),
+            released_at: self.clock.now(),
+        };
+        let release = sign_seat_release(release, &seat_token)?;
+
+        self.deactivate()?;
+        Ok(serde_json::to_string(&release).expect("seat release serializes"))
+    }
+
+    /// Reverts to the free tier and deletes the stored license file.
+    pub fn deactivate(&mut self) -> Result<(), LicenseError> {
+        if let Some(path) = &self.storage {
+            for path in [path.clone(), seat_path(path)] {
+                match fs::remove_file(&path) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(LicenseError::Io(e.to_string())),
+                }
+            }
+        }
+
+        self.license = free_license();
+        self.seat_token = None;
+        // A trial that is still running takes over again
+        if let Err(e) = self.load_trial() {
+            eprintln!("Error loading trial: {}", e);
+        }
+        Ok(())
+    }
+
+    /// Keeps the trial record in `path`, normally next to the license file.
+    pub fn with_trial_storage(mut self, path: PathBuf) -> Self {
+        self.trial_storage = Some(path);
+        self
+    }
+
+    /// Keeps a copy of the trial record in `path`, away from the trial
+    /// storage, so deleting the record does not give a fresh trial.
+    pub fn with_trial_marker(mut self, path: PathBuf) -> Self {
+        self.trial_marker = Some(path);
+        self
+    }
+
+    pub fn with_trial_days(mut self, days: u64) -> Self {
+        self.trial_days = days;
+        self
+    }
+
+    /// Starts the one trial this installation gets, unlocking every feature
+    /// for the trial period. Only possible from the free tier.
+    pub fn start_trial(&mut self) -> Result<(), LicenseError> {
+        let path = self
+            .trial_storage
+            .clone()
+            .ok_or_else(|| LicenseError::Io("no trial storage configured".to_string()))?;
+
+        if self.get_license_info().tier != LicenseTier::Free {
+            return Err(LicenseError::LicenseActive);
+        }
+        if path.exists() || self.trial_marker.as_ref().is_some_and(|marker| marker.exists()) {
+            return Err(LicenseError::TrialAlreadyUsed);
+        }
+
+        let now = self.clock.now();
+        let mut record = TrialRecord {
+            started_at: now,
+            expires_at: now.saturating_add(self.trial_days * SECONDS_PER_DAY),
+            last_seen: now,
+            mac: String::new(),
+        };
+        record.mac = trial_mac(self.fingerprint()?, &record);
+        self.save_trial(&record)?;
+
+        self.apply_trial(&record);
+        Ok(())
+    }
+
+    /// Keeps activated licenses in `path` so they survive restarts; call
+    /// `load` to restore a saved one.
+    pub fn with_storage(mut self, path: PathBuf) -> Self {
+        self.storage = Some(path);
+        self
+    }
+
+    /// Restores the license saved by a previous activation and returns
+    /// whether there was one. The saved file is the signed license itself,
+    /// so it is verified again; a file that was edited or corrupted is
+    /// rejected with the same errors as `activate_license` and the free tier
+    /// stays in effect. A revoked license is reported as `Revoked` and
+    /// downgraded, so the UI can say why.
+    pub fn load(&mut self) -> Result<bool, LicenseError> {
+        // Usage that cannot be read or trusted uses up the current windows
+        // rather than blocking the license or starting over
+        if let Err(e) = self.load_usage() {
+            eprintln!("Error loading quota usage: {}", e);
+            self.exhaust_usage();
         }
+        self.load_revocation_lists();
+
+        let path = match &self.storage {
+            Some(path) => path,
+            None => return Ok(false),
+        };
+
+        let license_file = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return self.load_trial(),
+            Err(e) => return Err(LicenseError::Io(e.to_string())),
+        };
+
+        let payload = verify_license(&license_file, &self.public_key)?;
+        let seat_token = match payload.seats {
+            Some(_) => Some(self.load_seat(path, &payload)?),
+            None => None,
+        };
+        self.apply(payload);
+        self.license.seat = seat_token.as_ref().map(|(token, _)| token.seat);
+        self.seat_token = seat_token.map(|(_, file)| file);
+        if self.enforce_revocation() {
+            return Err(LicenseError::Revoked);
+        }
+        Ok(true)
+    }
+
+    /// Reads the seat token of a stored team license, which must have been
+    /// issued to this machine, and returns it along with the file.
+    fn load_seat(&self, path: &Path, payload: &LicensePayload) -> Result<(SeatToken, String), LicenseError> {
+        let seat_token = match fs::read_to_string(seat_path(path)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(LicenseError::SeatRequired),
+            Err(e) => return Err(LicenseError::Io(e.to_string())),
+        };
+        let token = self.check_seat_token(&seat_token, payload)?;
+        Ok((token, seat_token))
+    }
+
+    /// Restores a trial started earlier, including one that has ended so
+    /// the downgrade is reported. A record that fails its MAC or shows the
+    /// clock was turned back ends the trial. A deleted record is restored
+    /// from the marker.
+    fn load_trial(&mut self) -> Result<bool, LicenseError> {
+        let path = match &self.trial_storage {
+            Some(path) => path.clone(),
+            None => return Ok(false),
+        };
+
+        let (contents, from_marker) = match read_optional(&path)? {
+            Some(contents) => (contents, false),
+            None => match self.trial_marker.as_deref().map(read_optional).transpose()?.flatten() {
+                Some(contents) => (contents, true),
+                None => return Ok(false),
+            },
+        };
+
+        let mut record: TrialRecord = serde_json::from_str(&contents).map_err(|_| LicenseError::TrialTampered)?;
+        if record.mac != trial_mac(self.fingerprint()?, &record) {
+            return Err(LicenseError::TrialTampered);
+        }
+
+        let now = self.clock.now();
+        if now.saturating_add(TRIAL_CLOCK_TOLERANCE) < record.last_seen {
+            return Err(LicenseError::TrialTampered);
+        }
+
+        if now > record.last_seen && now < record.expires_at {
+            record.last_seen = now;
+            record.mac = trial_mac(self.fingerprint()?, &record);
+            self.save_trial(&record)?;
+        } else if from_marker {
+            self.save_trial(&record)?;
+        }
+
+        self.apply_trial(&record);
+        Ok(true)
+    }
+
+    /// Writes the trial record and its marker.
+    fn save_trial(&self, record: &TrialRecord) -> Result<(), LicenseError> {
+        let json = serde_json::to_string(record).map_err(|e| LicenseError::Io(e.to_string()))?;
+        for path in self.trial_storage.iter().chain(&self.trial_marker) {
+            write_atomic(path, &json).map_err(|e| LicenseError::Io(e.to_string()))?;
+        }
+        Ok(())
+    }
+
+    fn apply_trial(&mut self, record: &TrialRecord) {
+        self.license = LicenseInfo {
+            is_premium: true,
+            features: Feature::ALL.to_vec(),
+            quotas: BTreeMap::new(),
+            expires_at: Some(record.expires_at),
+            license_id: None,
+            licensee: None,
+            tier: LicenseTier::Trial,
+            downgraded_from: None,
+            seats: None,
+            seat: None,
+            revoked: false,
+        };
+    }
+
+    fn apply(&mut self, payload: LicensePayload) {
+        let mut features = FREE_FEATURES.to_vec();
+        for feature in payload.features.iter().filter_map(Entitlement::known) {
+            if !features.contains(feature) {
+                features.push(*feature);
+            }
+        }
+        let quotas = payload
+            .quotas
+            .iter()
+            .filter_map(|(quota, limit)| Some((*quota.known()?, *limit)))
+            .collect();
+
+        self.license = LicenseInfo {
+            is_premium: payload.tier == LicenseTier::Premium,
+            features,
+            quotas,
+            expires_at: payload.expires_at,
+            license_id: Some(payload.license_id),
+            licensee: Some(payload.licensee),
+            tier: payload.tier,
+            downgraded_from: None,
+            seats: payload.seats,
+            seat: None,
+            revoked: false,
+        };
     }
 }
 
@@ -97,3 +1148,657 @@ impl Default for LicenseManager {
     }
 }
 
+fn free_license() -> LicenseInfo {
+    LicenseInfo {
+        is_premium: false,
+        features: FREE_FEATURES.to_vec(),
+        quotas: BTreeMap::new(),
+        expires_at: None,
+        license_id: None,
+        licensee: None,
+        tier: LicenseTier::Free,
+        downgraded_from: None,
+        seats: None,
+        seat: None,
+        revoked: false,
+    }
+}
+
+/// The seat token lives next to the license file, e.g. `license.seat`.
+fn seat_path(license_path: &Path) -> PathBuf {
+    license_path.with_extension("seat")
+}
+
+/// Contents of `path`, or `None` if it does not exist.
+fn read_optional(path: &Path) -> Result<Option<String>, LicenseError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(LicenseError::Io(e.to_string())),
+    }
+}
+
+/// HMAC-SHA256 over the record fields, keyed by the machine fingerprint so
+/// a record edited by hand or copied from another machine does not verify.
+fn trial_mac(fingerprint: &str, record: &TrialRecord) -> String {
+    machine_mac(
+        fingerprint,
+        &format!("{}:{}:{}", record.started_at, record.expires_at, record.last_seen),
+    )
+}
+
+/// Same as `trial_mac`, over the counters as canonical JSON. The prefix
+/// keeps a usage MAC from ever matching a trial MAC.
+fn usage_mac(fingerprint: &str, usage: &BTreeMap<Quota, QuotaCounter>) -> String {
+    let value = serde_json::to_value(usage).expect("quota usage serializes");
+    machine_mac(fingerprint, &format!("usage:{}", canonical_json(&value)))
+}
+
+fn machine_mac(fingerprint: &str, message: &str) -> String {
+    let key = Sha256::new()
+        .chain_update(TRIAL_KEY_CONTEXT)
+        .chain_update(fingerprint.as_bytes())
+        .finalize();
+    let mut mac = Hmac::<Sha256>::new_from_slice(&key).expect("HMAC accepts any key length");
+    mac.update(message.as_bytes());
+
+    mac.finalize()
+        .into_bytes()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Checks the signature of a license file and returns its payload.
+pub fn verify_license(license_file: &str, public_key: &VerifyingKey) -> Result<LicensePayload, LicenseError> {
+    let file: Value = serde_json::from_str(license_file)
+        .map_err(|e| LicenseError::Malformed(format!("invalid JSON: {}", e)))?;
+
+    let payload = file
+        .get("payload")
+        .filter(|p| p.is_object())
+        .ok_or_else(|| LicenseError::Malformed("missing payload".to_string()))?;
+    let signature = file
+        .get("signature")
+        .and_then(Value::as_str)
+        .ok_or_else(|| LicenseError::Malformed("missing signature".to_string()))?;
+    let signature = BASE64
+        .decode(signature)
+        .ok()
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| LicenseError::Malformed("signature is not a base64 Ed25519 signature".to_string()))?;
+
+    // Verify the payload exactly as written, so unknown fields are covered too
+    let typed: Result<LicensePayload, _> = serde_json::from_value(payload.clone());
+    if public_key
+        .verify(canonical_json(payload).as_bytes(), &signature)
+        .is_err()
+    {
+        return Err(match &typed {
+            Ok(p) if p.features_digest != features_digest(&p.features) => LicenseError::TamperedFeatures,
+            _ => LicenseError::InvalidSignature,
+        });
+    }
+
+    let payload = typed.map_err(|e| LicenseError::Malformed(format!("invalid payload: {}", e)))?;
+    if payload.features_digest != features_digest(&payload.features) {
+        return Err(LicenseError::Malformed("features digest does not match the feature list".to_string()));
+    }
+    Ok(payload)
+}
+
+/// Signs `payload` for distribution, filling in its feature digest.
+pub fn sign_license(mut payload: LicensePayload, signing_key: &SigningKey) -> SignedLicense {
+    payload.features_digest = features_digest(&payload.features);
+    let value = serde_json::to_value(&payload).expect("license payload serializes");
+    let signature = signing_key.sign(canonical_json(&value).as_bytes());
+
+    SignedLicense {
+        payload,
+        signature: BASE64.encode(signature.to_bytes()),
+    }
+}
+
+/// Checks the signature of a revocation list file and returns the list.
+pub fn verify_revocation_list(list_file: &str, public_key: &VerifyingKey) -> Result<RevocationList, LicenseError> {
+    let file: Value = serde_json::from_str(list_file)
+        .map_err(|e| LicenseError::Malformed(format!("invalid JSON: {}", e)))?;
+
+    let list = file
+        .get("list")
+        .filter(|l| l.is_object())
+        .ok_or_else(|| LicenseError::Malformed("missing list".to_string()))?;
+    let signature = file
+        .get("signature")
+        .and_then(Value::as_str)
+        .and_then(|s| BASE64.decode(s).ok())
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or_else(|| LicenseError::Malformed("missing or undecodable signature".to_string()))?;
+
+    public_key
+        .verify(canonical_json(list).as_bytes(), &signature)
+        .map_err(|_| LicenseError::InvalidSignature)?;
+    serde_json::from_value(list.clone()).map_err(|e| LicenseError::Malformed(format!("invalid list: {}", e)))
+}
+
+/// Signs a revocation list for distribution.
+pub fn sign_revocation_list(list: RevocationList, signing_key: &SigningKey) -> SignedRevocationList {
+    let value = serde_json::to_value(&list).expect("revocation list serializes");
+    let signature = signing_key.sign(canonical_json(&value).as_bytes());
+
+    SignedRevocationList {
+        list,
+        signature: BASE64.encode(signature.to_bytes()),
+    }
+}
+
+/// Checks the signature of a seat token file and returns the token.
+pub fn verify_seat_token(token_file: &str, public_key: &VerifyingKey) -> Result<SeatToken, LicenseError> {
+    let file: Value = serde_json::from_str(token_file).map_err(|_| LicenseError::InvalidSeatToken)?;
+    let token = file
+        .get("token")
+        .filter(|t| t.is_object())
+        .ok_or(LicenseError::InvalidSeatToken)?;
+    let signature = file
+        .get("signature")
+        .and_then(Value::as_str)
+        .and_then(|s| BASE64.decode(s).ok())
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or(LicenseError::InvalidSeatToken)?;
+
+    public_key
+        .verify(canonical_json(token).as_bytes(), &signature)
+        .map_err(|_| LicenseError::InvalidSeatToken)?;
+    serde_json::from_value(token.clone()).map_err(|_| LicenseError::InvalidSeatToken)
+}
+
+/// Signs a seat token for the machine it names.
+pub fn sign_seat_token(token: SeatToken, signing_key: &SigningKey) -> SignedSeatToken {
+    let value = serde_json::to_value(&token).expect("seat token serializes");
+    let signature = signing_key.sign(canonical_json(&value).as_bytes());
+
+    SignedSeatToken {
+        token,
+        signature: BASE64.encode(signature.to_bytes()),
+    }
+}
+
+/// Checks a seat release file and returns the release. The seat token it
+/// carries must be signed with the license key and be for the seat and
+/// machine the release names, and the release must be signed with the key
+/// bound to that token.
+pub fn verify_seat_release(release_file: &str, public_key: &VerifyingKey) -> Result<SeatRelease, LicenseError> {
+    let file: Value = serde_json::from_str(release_file).map_err(|_| LicenseError::InvalidSeatRelease)?;
+    let release = file
+        .get("release")
+        .filter(|r| r.is_object())
+        .ok_or(LicenseError::InvalidSeatRelease)?;
+    let seat_token = file.get("seat_token").ok_or(LicenseError::InvalidSeatRelease)?;
+    let signature = file
+        .get("signature")
+        .and_then(Value::as_str)
+        .and_then(|s| BASE64.decode(s).ok())
+        .and_then(|bytes| Signature::from_slice(&bytes).ok())
+        .ok_or(LicenseError::InvalidSeatRelease)?;
+
+    let token = verify_seat_token(&seat_token.to_string(), public_key).map_err(|_| LicenseError::InvalidSeatRelease)?;
+    let typed: SeatRelease = serde_json::from_value(release.clone()).map_err(|_| LicenseError::InvalidSeatRelease)?;
+    if typed.license_id != token.license_id || typed.seat != token.seat {
+        return Err(LicenseError::InvalidSeatRelease);
+    }
+    if typed.fingerprint != token.fingerprint {
+        return Err(LicenseError::WrongMachine);
+    }
+
+    let signing_key = seat_release_key(seat_token).ok_or(LicenseError::InvalidSeatRelease)?;
+    signing_key
+        .verifying_key()
+        .verify(canonical_json(release).as_bytes(), &signature)
+        .map_err(|_| LicenseError::InvalidSeatRelease)?;
+    Ok(typed)
+}
+
+/// Signs a seat release with the key bound to `seat_token`, the seat token
+/// file the releasing machine activated its seat with.
+pub fn sign_seat_release(release: SeatRelease, seat_token: &str) -> Result<SignedSeatRelease, LicenseError> {
+    let seat_token: Value = serde_json::from_str(seat_token).map_err(|_| LicenseError::InvalidSeatToken)?;
+    let signing_key = seat_release_key(&seat_token).ok_or(LicenseError::InvalidSeatToken)?;
+    let value = serde_json::to_value(&release).expect("seat release serializes");
+    let signature = signing_key.sign(canonical_json(&value).as_bytes());
+
+    Ok(SignedSeatRelease {
+        release,
+        seat_token,
+        signature: BASE64.encode(signature.to_bytes()),
+    })
+}
+
+/// Ed25519 key derived from a seat token's signature and machine. Like the
+/// trial record MAC this makes releases tamper-evident rather than secret:
+/// whoever holds the token file can derive the key.
+fn seat_release_key(seat_token: &Value) -> Option<SigningKey> {
+    let signature = seat_token.get("signature")?.as_str()?;
+    let fingerprint = seat_token.get("token")?.get("fingerprint")?.as_str()?;
+    let seed = Sha256::new()
+        .chain_update(SEAT_RELEASE_KEY_CONTEXT)
+        .chain_update(signature.as_bytes())
+        .chain_update(fingerprint.as_bytes())
+        .finalize();
+    Some(SigningKey::from_bytes(&seed.into()))
+}
+
+/// Hex SHA-256 over the feature names sorted and joined with newlines.
+pub fn features_digest(features: &[Entitlement<Feature>]) -> String {
+    let mut sorted: Vec<String> = features.iter().map(|f| f.to_string()).collect();
+    sorted.sort_unstable();
+
+    Sha256::digest(sorted.join("\n").as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// JSON with object keys sorted and no insignificant whitespace, so the
+/// signed bytes do not depend on how the file was formatted.
+pub fn canonical_json(value: &Value) -> String {
+    let mut out = String::new();
+    write_canonical(value, &mut out);
+    out
+}
+
+fn write_canonical(value: &Value, out: &mut String) {
+    match value {
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    fn keypair() -> (SigningKey, VerifyingKey) {
+        let signing_key = SigningKey::from_bytes(&[42; 32]);
+        let public_key = signing_key.verifying_key();
+        (signing_key, public_key)
+    }
+
+    fn payload() -> LicensePayload {
+        LicensePayload {
+            license_id: "lic-001".to_string(),
+            licensee: "ACME".to_string(),
+            tier: LicenseTier::Premium,
+            features: vec![Feature::AIChat.into(), Feature::CustomThemes.into()],
+            quotas: BTreeMap::new(),
+            issued_at: 1_700_000_000,
+            expires_at: Some(1_800_000_000),
+            seats: None,
+            features_digest: String::new(),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("meacode-license-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    const DAY_ONE: u64 = 1_750_000_000 - 1_750_000_000 % SECONDS_PER_DAY;
+
+    /// A manager for a license allowing 2 AI requests a day, keeping its
+    /// license and usage in `dir`. The license is activated on first use and
+    /// loaded afterwards, like across restarts.
+    fn quota_manager(dir: &Path, fingerprint: &str, now: &Arc<AtomicU64>) -> LicenseManager {
+        let (signing_key, public_key) = keypair();
+        let now = now.clone();
+        let mut manager = LicenseManager::new()
+            .with_public_key(public_key)
+            .with_fingerprint(fingerprint.to_string())
+            .with_clock(Arc::new(move || now.load(Ordering::SeqCst)))
+            .with_storage(dir.join("license.json"))
+            .with_usage_storage(dir.join("usage.json"));
+
+        if !manager.load().unwrap() {
+            let mut payload = payload();
+            payload.quotas.insert(Quota::AiRequestsPerDay.into(), 2);
+            let file = serde_json::to_string(&sign_license(payload, &signing_key)).unwrap();
+            manager.activate_license(&file).unwrap();
+        }
+        manager
+    }
+
+    fn edit_usage(dir: &Path, edit: impl FnOnce(&mut Value)) {
+        let path = dir.join("usage.json");
+        let mut record: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        edit(&mut record);
+        fs::write(path, record.to_string()).unwrap();
+    }
+
+    /// A signed license file as JSON, to edit before verifying.
+    fn license_file(signing_key: &SigningKey) -> Value {
+        serde_json::to_value(sign_license(payload(), signing_key)).unwrap()
+    }
+
+    #[test]
+    fn signed_license_verifies() {
+        let (signing_key, public_key) = keypair();
+        let file = license_file(&signing_key).to_string();
+
+        let verified = verify_license(&file, &public_key).unwrap();
+
+        assert_eq!(verified.license_id, "lic-001");
+        assert_eq!(verified.features_digest, features_digest(&payload().features));
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        let (signing_key, public_key) = keypair();
+        let mut bad_signature = license_file(&signing_key);
+        bad_signature["signature"] = json!("not base64!");
+        let mut short_signature = license_file(&signing_key);
+        short_signature["signature"] = json!(BASE64.encode([1, 2, 3]));
+
+        let files = [
+            "not json".to_string(),
+            json!({ "signature": "" }).to_string(),
+            json!({ "payload": [], "signature": "" }).to_string(),
+            json!({ "payload": license_file(&signing_key)["payload"] }).to_string(),
+            bad_signature.to_string(),
+            short_signature.to_string(),
+        ];
+
+        for file in files {
+            assert!(
+                matches!(verify_license(&file, &public_key), Err(LicenseError::Malformed(_))),
+                "{}",
+                file
+            );
+        }
+    }
+
+    #[test]
+    fn signature_from_another_key_is_invalid() {
+        let (_, public_key) = keypair();
+        let other_key = SigningKey::from_bytes(&[7; 32]);
+        let file = license_file(&other_key).to_string();
+
+        assert_eq!(verify_license(&file, &public_key), Err(LicenseError::InvalidSignature));
+    }
+
+    #[test]
+    fn edited_payload_is_invalid() {
+        let (signing_key, public_key) = keypair();
+
+        let mut licensee = license_file(&signing_key);
+        licensee["payload"]["licensee"] = json!("Someone else");
+        let mut expiry = license_file(&signing_key);
+        expiry["payload"]["expires_at"] = Value::Null;
+        let mut extra_field = license_file(&signing_key);
+        extra_field["payload"]["unlimited"] = json!(true);
+
+        for file in [licensee, expiry, extra_field] {
+            assert_eq!(verify_license(&file.to_string(), &public_key), Err(LicenseError::InvalidSignature));
+        }
+    }
+
+    #[test]
+    fn edited_feature_list_is_reported_as_tampered() {
+        let (signing_key, public_key) = keypair();
+        let mut file = license_file(&signing_key);
+        file["payload"]["features"].as_array_mut().unwrap().push(json!("CloudSync"));
+
+        assert_eq!(verify_license(&file.to_string(), &public_key), Err(LicenseError::TamperedFeatures));
+    }
+
+    #[test]
+    fn canonical_json_ignores_key_order_and_whitespace() {
+        let a: Value = serde_json::from_str(r#"{"b": [1, {"y": null, "x": "s p"}], "a": true}"#).unwrap();
+        let b: Value = serde_json::from_str("{\n  \"a\":true,\n  \"b\":[ 1,{\"x\":\"s p\",\"y\":null} ]\n}").unwrap();
+
+        assert_eq!(canonical_json(&a), r#"{"a":true,"b":[1,{"x":"s p","y":null}]}"#);
+        assert_eq!(canonical_json(&a), canonical_json(&b));
+    }
+
+    #[test]
+    fn signature_survives_reformatting_the_file() {
+        let (signing_key, public_key) = keypair();
+        let file = license_file(&signing_key);
+
+        // Rebuild the payload with its keys reversed and pretty-print the file
+        let fields = file["payload"].as_object().unwrap();
+        let reversed: String = fields
+            .iter()
+            .rev()
+            .map(|(key, value)| format!("    {}: {}", Value::String(key.clone()), value))
+            .collect::<Vec<_>>()
+            .join(",\n");
+        let reformatted = format!(
+            "{{\n  \"signature\": {},\n  \"payload\": {{\n{}\n  }}\n}}\n",
+            file["signature"], reversed
+        );
+        assert_ne!(reformatted, file.to_string());
+
+        assert_eq!(verify_license(&reformatted, &public_key).unwrap(), sign_license(payload(), &signing_key).payload);
+    }
+
+    #[test]
+    fn quota_usage_survives_a_restart() {
+        let dir = temp_dir("usage-restart");
+        let now = Arc::new(AtomicU64::new(DAY_ONE + 60));
+
+        let mut manager = quota_manager(&dir, "machine-a", &now);
+        manager.consume(Quota::AiRequestsPerDay).unwrap();
+
+        let mut manager = quota_manager(&dir, "machine-a", &now);
+        manager.consume(Quota::AiRequestsPerDay).unwrap();
+        let exceeded = manager.consume(Quota::AiRequestsPerDay).unwrap_err();
+        assert_eq!(exceeded.resets_at, Some(DAY_ONE + SECONDS_PER_DAY));
+
+        now.store(DAY_ONE + SECONDS_PER_DAY, Ordering::SeqCst);
+        assert!(manager.consume(Quota::AiRequestsPerDay).is_ok());
+    }
+
+    #[test]
+    fn untrusted_usage_uses_up_the_current_window() {
+        type Edit = fn(&mut Value);
+        let now = Arc::new(AtomicU64::new(DAY_ONE + 60));
+        let edits: [(&str, Edit); 3] = [
+            ("edited", |record| record["usage"]["ai_requests_per_day"]["used"] = json!(0)),
+            ("no-mac", |record| *record = record["usage"].clone()),
+            ("garbage", |record| *record = json!("garbage")),
+        ];
+
+        for (name, edit) in edits {
+            let dir = temp_dir(&format!("usage-{}", name));
+            quota_manager(&dir, "machine-a", &now).consume(Quota::AiRequestsPerDay).unwrap();
+            edit_usage(&dir, edit);
+
+            let mut manager = quota_manager(&dir, "machine-a", &now);
+            assert!(manager.consume(Quota::AiRequestsPerDay).is_err(), "{}", name);
+            assert_eq!(manager.quota_usage()[0].used, Some(2), "{}", name);
+        }
+    }
+
+    #[test]
+    fn usage_copied_from_another_machine_is_used_up() {
+        let dir = temp_dir("usage-copied");
+        let now = Arc::new(AtomicU64::new(DAY_ONE + 60));
+        quota_manager(&dir, "machine-a", &now).consume(Quota::AiRequestsPerDay).unwrap();
+
+        let mut manager = quota_manager(&dir, "machine-b", &now);
+        assert!(manager.consume(Quota::AiRequestsPerDay).is_err());
+
+        // The record rewritten on the new machine holds for the next window
+        now.store(DAY_ONE + SECONDS_PER_DAY, Ordering::SeqCst);
+        manager.consume(Quota::AiRequestsPerDay).unwrap();
+        let mut manager = quota_manager(&dir, "machine-b", &now);
+        manager.consume(Quota::AiRequestsPerDay).unwrap();
+        assert!(manager.consume(Quota::AiRequestsPerDay).is_err());
+    }
+
+    #[test]
+    fn clock_turned_back_a_day_does_not_reset_usage() {
+        let dir = temp_dir("usage-clock");
+        let now = Arc::new(AtomicU64::new(DAY_ONE + SECONDS_PER_DAY + 60));
+        quota_manager(&dir, "machine-a", &now).consume(Quota::AiRequestsPerDay).unwrap();
+
+        now.store(DAY_ONE + 60, Ordering::SeqCst);
+        let mut manager = quota_manager(&dir, "machine-a", &now);
+        let exceeded = manager.consume(Quota::AiRequestsPerDay).unwrap_err();
+        assert_eq!(exceeded.resets_at, Some(DAY_ONE + 2 * SECONDS_PER_DAY));
+        assert_eq!(manager.quota_usage()[0].used, Some(2));
+
+        // Back on the stored day the real count applies again
+        now.store(DAY_ONE + SECONDS_PER_DAY + 120, Ordering::SeqCst);
+        manager.consume(Quota::AiRequestsPerDay).unwrap();
+        assert!(manager.consume(Quota::AiRequestsPerDay).is_err());
+    }
+
+    fn seat_token(seat: u32, fingerprint: &str, issued_at: u64) -> String {
+        let (signing_key, _) = keypair();
+        let token = SeatToken {
+            license_id: "lic-001".to_string(),
+            seat,
+            fingerprint: fingerprint.to_string(),
+            issued_at,
+        };
+        serde_json::to_string(&sign_seat_token(token, &signing_key)).unwrap()
+    }
+
+    fn team_license() -> String {
+        let (signing_key, _) = keypair();
+        let mut payload = payload();
+        payload.seats = Some(3);
+        serde_json::to_string(&sign_license(payload, &signing_key)).unwrap()
+    }
+
+    fn team_manager(dir: &Path, fingerprint: &str, now: u64) -> LicenseManager {
+        let (_, public_key) = keypair();
+        LicenseManager::new()
+            .with_public_key(public_key)
+            .with_fingerprint(fingerprint.to_string())
+            .with_clock(Arc::new(move || now))
+            .with_storage(dir.join("license.json"))
+    }
+
+    /// A release of seat 1 made on machine-a at `DAY_ONE + 100`.
+    fn released_seat(name: &str) -> String {
+        let dir = temp_dir(name);
+        let mut manager = team_manager(&dir, "machine-a", DAY_ONE + 100);
+        manager
+            .activate_seat(&team_license(), &seat_token(1, "machine-a", DAY_ONE), None)
+            .unwrap();
+
+        // The token is kept with the license, so the seat can be released after a restart
+        let mut manager = team_manager(&dir, "machine-a", DAY_ONE + 100);
+        assert!(manager.load().unwrap());
+        let release = manager.release_seat().unwrap();
+        assert_eq!(manager.get_license_info().tier, LicenseTier::Free);
+        release
+    }
+
+    #[test]
+    fn released_seat_moves_to_another_machine() {
+        let (_, public_key) = keypair();
+        let release = released_seat("seat-moves");
+
+        let verified = verify_seat_release(&release, &public_key).unwrap();
+        assert_eq!((verified.seat, verified.fingerprint.as_str()), (1, "machine-a"));
+
+        let mut manager = team_manager(&temp_dir("seat-moves-b"), "machine-b", DAY_ONE + 200);
+        manager
+            .activate_seat(&team_license(), &seat_token(1, "machine-b", DAY_ONE + 150), Some(&release))
+            .unwrap();
+        assert_eq!(manager.get_license_info().seat, Some(1));
+    }
+
+    #[test]
+    fn tampered_seat_release_is_rejected() {
+        let (_, public_key) = keypair();
+        let release: Value = serde_json::from_str(&released_seat("seat-tampered")).unwrap();
+
+        let mut released_at = release.clone();
+        released_at["release"]["released_at"] = json!(0);
+        let mut seat = release.clone();
+        seat["release"]["seat"] = json!(2);
+        let mut signature = release.clone();
+        signature["signature"] = json!(BASE64.encode([0; 64]));
+        let mut forged_token = release.clone();
+        forged_token["seat_token"] = serde_json::from_str(&seat_token(1, "machine-a", DAY_ONE + 1)).unwrap();
+        let mut unsigned_token = release.clone();
+        unsigned_token["seat_token"]["token"]["seat"] = json!(2);
+        unsigned_token["release"]["seat"] = json!(2);
+
+        for file in [released_at, seat, signature, forged_token, unsigned_token] {
+            assert_eq!(
+                verify_seat_release(&file.to_string(), &public_key),
+                Err(LicenseError::InvalidSeatRelease),
+                "{}",
+                file
+            );
+        }
+    }
+
+    #[test]
+    fn seat_release_from_the_wrong_machine_is_rejected() {
+        let (_, public_key) = keypair();
+        let release = SeatRelease {
+            license_id: "lic-001".to_string(),
+            seat: 1,
+            fingerprint: "machine-b".to_string(),
+            released_at: DAY_ONE + 100,
+        };
+        // machine-b signing a release with machine-a's token file
+        let forged = sign_seat_release(release, &seat_token(1, "machine-a", DAY_ONE)).unwrap();
+        let forged = serde_json::to_string(&forged).unwrap();
+        assert_eq!(verify_seat_release(&forged, &public_key), Err(LicenseError::WrongMachine));
+
+        // Editing the machine of a genuine release
+        let mut edited: Value = serde_json::from_str(&released_seat("seat-wrong-machine")).unwrap();
+        edited["release"]["fingerprint"] = json!("machine-c");
+        assert_eq!(verify_seat_release(&edited.to_string(), &public_key), Err(LicenseError::WrongMachine));
+
+        let mut manager = team_manager(&temp_dir("seat-wrong-machine-b"), "machine-b", DAY_ONE + 200);
+        let result = manager.activate_seat(&team_license(), &seat_token(1, "machine-b", DAY_ONE + 150), Some(&forged));
+        assert_eq!(result, Err(LicenseError::WrongMachine));
+        assert_eq!(manager.get_license_info().tier, LicenseTier::Free);
+    }
+
+    #[test]
+    fn seat_release_must_match_the_new_seat_token() {
+        let release = released_seat("seat-mismatch");
+        let mut manager = team_manager(&temp_dir("seat-mismatch-b"), "machine-b", DAY_ONE + 200);
+
+        // Another seat, and a token issued before the seat was released
+        for token in [seat_token(2, "machine-b", DAY_ONE + 150), seat_token(1, "machine-b", DAY_ONE + 50)] {
+            let result = manager.activate_seat(&team_license(), &token, Some(&release));
+            assert_eq!(result, Err(LicenseError::InvalidSeatRelease));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::fs;
+
+use sha2::{Digest, Sha256};
+
+/// Mixed into the fingerprint so it cannot be matched against the raw OS id.
+const FINGERPRINT_CONTEXT: &[u8] = b"meacode-studio machine v1";
+
+/// Hex SHA-256 of the operating system's machine id. It is derived locally,
+/// survives reinstalling the app and changes when the OS is reinstalled.
+/// Falls back to the host name where no machine id is available, and fails
+/// when there is neither, since every such machine would share one fingerprint.
+pub fn machine_fingerprint() -> anyhow::Result<String> {
+    let sources: [fn() -> Option<String>; 4] = [
+        machine_id,
+        || std::env::var("COMPUTERNAME").ok(),
+        || std::env::var("HOSTNAME").ok(),
+        || fs::read_to_string("/etc/hostname").ok(),
+    ];
+    let id = sources
+        .iter()
+        .filter_map(|source| source())
+        .map(|id| id.trim().to_string())
+        .find(|id| !id.is_empty())
+        .ok_or_else(|| anyhow::anyhow!("No machine id or host name found"))?;
+
+    Ok(Sha256::new()
+        .chain_update(FINGERPRINT_CONTEXT)
+        .chain_update(id.as_bytes())
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+#[cfg(target_os = "linux")]
+fn machine_id() -> Option<String> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .filter_map(|path| fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .find(|id| !id.is_empty())
+}
+
+#[cfg(target_os = "macos")]
+fn machine_id() -> Option<String> {
+    let output = std::process::Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .ok()?;
+
+    // "IOPlatformUUID" = "XXXXXXXX-XXXX-..."
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("IOPlatformUUID"))
+        .and_then(|line| line.rsplit('"').nth(1))
+        .map(str::to_string)
+}
+
+#[cfg(windows)]
+fn machine_id() -> Option<String> {
+    let output = std::process::Command::new("reg")
+        .args(["query", r"HKLM\SOFTWARE\Microsoft\Cryptography", "/v", "MachineGuid"])
+        .output()
+        .ok()?;
+
+    // MachineGuid    REG_SZ    xxxxxxxx-xxxx-...
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("MachineGuid"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(str::to_string)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn machine_id() -> Option<String> {
+    let output = std::process::Command::new("hostid").output().ok()?;
+    let id = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Some(id).filter(|id| !id.is_empty())
+}
@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::comments::strip_comments_and_strings;
+use crate::indexer::{IndexedFile, ProjectIndexer, SymbolKind};
+use crate::workspace::SearchScope;
+
+/// Keywords and operators that add a branch to the control flow graph.
+const DECISION_KEYWORDS: &[&str] = &[
+    "if", "elif", "for", "foreach", "while", "case", "catch", "except", "and", "or",
+];
+const DECISION_OPERATORS: &[&str] = &["&&", "||", "=>"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolMetrics {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// 1-based, inclusive line range of the definition and its body.
+    pub start_line: u32,
+    pub end_line: u32,
+    /// Non-blank, non-comment lines.
+    pub loc: u32,
+    pub cyclomatic_complexity: u32,
+    pub max_nesting_depth: u32,
+    pub parameter_count: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMetrics {
+    pub path: PathBuf,
+    pub relative_path: PathBuf,
+    pub root: Option<String>,
+    pub language: String,
+    pub loc: u32,
+    pub functions: u32,
+    pub total_complexity: u32,
+    pub max_complexity: u32,
+    pub max_nesting_depth: u32,
+    pub symbols: Vec<SymbolMetrics>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryMetrics {
+    pub root: Option<String>,
+    /// Directory relative to `root`; empty for the root itself.
+    pub path: PathBuf,
+    pub files: u32,
+    pub loc: u32,
+    pub functions: u32,
+    pub total_complexity: u32,
+    pub max_complexity: u32,
+    pub average_complexity: f64,
+}
+
+impl ProjectIndexer {
+    pub async fn file_metrics(&self, path: &Path) -> Option<FileMetrics> {
+        let files = self.files.read().await;
+        files.get(path).map(|file| self.compute_file_metrics(file))
+    }
+
+    /// Metrics for every indexed file in `scope`, sorted by relative path.
+    pub async fn workspace_metrics(&self, scope: &SearchScope) -> Vec<FileMetrics> {
+        let mut metrics: Vec<FileMetrics> = self
+            .files_in(scope)
+            .await
+            .iter()
+            .map(|file| self.compute_file_metrics(file))
+            .collect();
+        metrics.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        metrics
+    }
+
+    /// Aggregates file metrics into every ancestor directory of each file.
+    pub async fn directory_metrics(&self, scope: &SearchScope) -> Vec<DirectoryMetrics> {
+        let mut dirs: BTreeMap<(Option<String>, PathBuf), DirectoryMetrics> = BTreeMap::new();
+
+        for file in self.workspace_metrics(scope).await {
+            let mut dir = file.relative_path.parent().map(Path::to_path_buf);
+            while let Some(path) = dir {
+                let entry = dirs
+                    .entry((file.root.clone(), path.clone()))
+                    .or_insert_with(|| DirectoryMetrics {
+                        root: file.root.clone(),
+                        path: path.clone(),
+                        files: 0,
+                        loc: 0,
+                        functions: 0,
+                        total_complexity: 0,
+                        max_complexity: 0,
+                        average_complexity: 0.0,
+                    });
+                entry.files += 1;
+                entry.loc += file.loc;
+                entry.functions += file.functions;
+                entry.total_complexity += file.total_complexity;
+                entry.max_complexity = entry.max_complexity.max(file.max_complexity);
+
+                dir = path.parent().map(Path::to_path_buf);
+            }
+        }
+
+        dirs.into_values()
+            .map(|mut d| {
+                if d.functions > 0 {
+                    d.average_complexity = d.total_complexity as f64 / d.functions as f64;
+                }
+                d
+            })
+            .collect()
+    }
+
+    fn compute_file_metrics(&self, file: &IndexedFile) -> FileMetrics {
+        let code = match self.languages().get(&file.language) {
+            Some(language) => strip_comments_and_strings(&file.content, language),
+            None => file.content.clone(),
+        };
+        let lines: Vec<&str> = code.lines().collect();
+
+        let symbols: Vec<SymbolMetrics> = file
+            .symbols
+            .iter()
+            .filter(|s| matches!(s.kind, SymbolKind::Function | SymbolKind::Class | SymbolKind::Module))
+            .filter_map(|s| {
+                let line_idx = (s.line as usize).checked_sub(1).filter(|&i| i < lines.len())?;
+                // Imported columns may fall past the line end or inside a character
+                let header = lines[line_idx].get(s.column as usize..)?;
+                let body = find_body(&lines, line_idx, s.column as usize);
+                let body_lines = &lines[line_idx..=body.end_line];
+
+                Some(SymbolMetrics {
+                    name: s.name.clone(),
+                    kind: s.kind.clone(),
+                    start_line: s.line,
+                    end_line: body.end_line as u32 + 1,
+                    loc: count_loc(body_lines),
+                    cyclomatic_complexity: 1 + count_decisions(body_lines, &file.language),
+                    max_nesting_depth: body.max_depth,
+                    parameter_count: count_parameters(header),
+                })
+            })
+            .collect();
+
+        let functions: Vec<&SymbolMetrics> = symbols
+            .iter()
+            .filter(|s| matches!(s.kind, SymbolKind::Function))
+            .collect();
+
+        FileMetrics {
+            path: file.path.clone(),
+            relative_path: file.relative_path.clone(),
+            root: file.root.clone(),
+            language: file.language.clone(),
+            loc: count_loc(&lines),
+            functions: functions.len() as u32,
+            total_complexity: functions.iter().map(|s| s.cyclomatic_complexity).sum(),
+            max_complexity: functions.iter().map(|s| s.cyclomatic_complexity).max().unwrap_or(0),
+            max_nesting_depth: symbols.iter().map(|s| s.max_nesting_depth).max().unwrap_or(0),
+            symbols,
+        }
+    }
+}
+
+struct Body {
+    /// 0-based index of the last line of the body.
+    end_line: usize,
+    max_depth: u32,
+}
+
+/// Finds where a definition ends: at the matching `}` for brace languages, at
+/// the end of the indented block when the header ends with `:`, or on the
+/// same line for declarations without a body.
+fn find_body(lines: &[&str], start: usize, column: usize) -> Body {
+    let mut depth = 0u32;
+    let mut max_depth = 0u32;
+    let mut opened = false;
+
+    let base = indentation(lines[start]);
+
+    for (idx, line) in lines.iter().enumerate().skip(start) {
+        let text = if idx == start { line.get(column..).unwrap_or("") } else { line };
+
+        // A header continues on deeper-indented lines or on lines that carry
+        // on the signature; anything else means there was no body
+        if idx > start && !opened && !line.trim().is_empty() && indentation(line) <= base {
+            let trimmed = line.trim_start();
+            if !["{", ")", "->", "where"].iter().any(|p| trimmed.starts_with(p)) {
+                return Body {
+                    end_line: idx - 1,
+                    max_depth: 0,
+                };
+            }
+        }
+
+        for c in text.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                    max_depth = max_depth.max(depth);
+                }
+                '}' if depth > 0 => {
+                    depth -= 1;
+                    if opened && depth == 0 {
+                        return Body {
+                            end_line: idx,
+                            max_depth: max_depth.saturating_sub(1),
+                        };
+                    }
+                }
+                ';' if !opened => {
+                    return Body {
+                        end_line: idx,
+                        max_depth: 0,
+                    };
+                }
+                _ => {}
+            }
+        }
+
+        if !opened && text.trim_end().ends_with(':') {
+            return indented_body(lines, start, idx);
+        }
+    }
+
+    Body {
+        end_line: if opened { lines.len() - 1 } else { start },
+        max_depth: max_depth.saturating_sub(1),
+    }
+}
+
+/// Body of an indentation-based definition such as a Python `def`.
+fn indented_body(lines: &[&str], start: usize, header_end: usize) -> Body {
+    let base = indentation(lines[start]);
+    let mut end_line = header_end;
+    let mut levels: Vec<usize> = Vec::new();
+    let mut max_depth = 0u32;
+
+    for (idx, line) in lines.iter().enumerate().skip(header_end + 1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let indent = indentation(line);
+        if indent <= base {
+            break;
+        }
+        end_line = idx;
+
+        while levels.last().map(|&l| l >= indent).unwrap_or(false) {
+            levels.pop();
+        }
+        levels.push(indent);
+        max_depth = max_depth.max(levels.len() as u32 - 1);
+    }
+
+    Body { end_line, max_depth }
+}
+
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+fn count_loc(lines: &[&str]) -> u32 {
+    lines.iter().filter(|l| !l.trim().is_empty()).count() as u32
+}
+
+fn count_decisions(lines: &[&str], language: &str) -> u32 {
+    let mut count = 0;
+
+    for line in lines {
+        for word in line
+            .split(|c: char| !c.is_alphanumeric() && c != '_')
+            .filter(|w| !w.is_empty())
+        {
+            if DECISION_KEYWORDS.contains(&word) {
+                count += 1;
+            }
+        }
+
+        for op in DECISION_OPERATORS {
+            // Only Rust uses `=>` for match arms; elsewhere it is an arrow function
+            if *op == "=>" && language != "rust" {
+                continue;
+            }
+            count += line.matches(op).count() as u32;
+        }
+
+        // Ternary `?`; Rust uses `?` for error propagation instead
+        if language != "rust" {
+            count += line
+                .match_indices('?')
+                .filter(|(i, _)| !line[i + 1..].starts_with(['.', '?']))
+                .count() as u32;
+        }
+    }
+
+    count
+}
+
+/// Counts the parameters in the first parenthesized list after the symbol
+/// name, ignoring receivers such as `self` and `this`.
+fn count_parameters(header: &str) -> u32 {
+    let open = match header.find('(') {
+        Some(open) => open,
+        None => return 0,
+    };
+
+    let mut depth = 0i32;
+    let mut params = Vec::new();
+    let mut current = String::new();
+    let mut prev = ' ';
+    for c in header[open + 1..].chars() {
+        match c {
+            '(' | '[' | '{' | '<' => {
+                depth += 1;
+                current.push(c);
+            }
+            ')' if depth == 0 => break,
+            // Arrows such as `->` and `=>` are not closing brackets
+            '>' if prev == '-' || prev == '=' => current.push(c),
+            ')' | ']' | '}' | '>' => {
+                depth -= 1;
+                current.push(c);
+            }
+            ',' if depth == 0 => params.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+        prev = c;
+    }
+    params.push(current);
+
+    params
+        .iter()
+        .map(|p| p.trim())
+        .filter(|p| !p.is_empty())
+        .filter(|p| {
+            let name = p.trim_start_matches('&').trim_start_matches("mut ").trim();
+            !matches!(name, "self" | "cls" | "this") && !name.starts_with("self:")
+        })
+        .count() as u32
+}
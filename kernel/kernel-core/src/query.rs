@@ -0,0 +1,427 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::indexer::{IndexedFile, ProjectIndexer, Symbol, SymbolKind};
+use crate::ranking::{self, Bm25};
+use crate::workspace::{glob_match, SearchScope};
+
+/// A parsed query such as `kind:function lang:rust path:kernel/ name:~index`.
+///
+/// Clauses are whitespace separated and all must match; a leading `-`
+/// negates a clause. Values may be double-quoted to include spaces. Bare
+/// words search file contents like `text:`.
+///
+/// | Field   | Matches                                                        |
+/// |---------|----------------------------------------------------------------|
+/// | `kind:` | symbol kind (`function`, `class`, `variable`, `constant`, `module`) |
+/// | `name:` | symbol name; exact, `~substring`, or `/regex/`                  |
+/// | `lang:` | language ID of the file                                        |
+/// | `path:` | substring of the relative path, or a glob with `*` and `?`     |
+/// | `root:` | name of the workspace root                                     |
+/// | `text:` | case-insensitive substring of the content, or `/regex/`        |
+#[derive(Debug, Clone)]
+pub struct Query {
+    pub clauses: Vec<Clause>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Clause {
+    pub negated: bool,
+    pub filter: Filter,
+    /// Byte range of the clause in the query string.
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Filter {
+    Kind(SymbolKind),
+    Name(Pattern),
+    Lang(String),
+    Path(String),
+    Root(String),
+    Text(Pattern),
+}
+
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Exact(String),
+    Contains(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    fn matches(&self, text: &str) -> bool {
+        match self {
+            Pattern::Exact(value) => text.eq_ignore_ascii_case(value),
+            Pattern::Contains(value) => text.to_lowercase().contains(&value.to_lowercase()),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+
+    /// 1-based line and byte column of the first match in `content`.
+    fn locate(&self, content: &str) -> Option<(u32, u32)> {
+        content.lines().enumerate().find_map(|(idx, line)| {
+            let column = match self {
+                Pattern::Exact(value) | Pattern::Contains(value) => line.to_lowercase().find(&value.to_lowercase()),
+                Pattern::Regex(regex) => regex.find(line).map(|m| m.start()),
+            }?;
+            Some((idx as u32 + 1, column as u32))
+        })
+    }
+}
+
+/// A query syntax error at a byte range of the input.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueryError {
+    pub message: String,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at column {}", self.message, self.start + 1)
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// A file, or a symbol within it when the query filters on symbols.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueryHit {
+    pub path: PathBuf,
+    pub relative_path: PathBuf,
+    pub root: Option<String>,
+    pub language: String,
+    pub symbol: Option<QuerySymbol>,
+    /// 1-based line of the symbol or of the first text match.
+    pub line: Option<u32>,
+    pub column: Option<u32>,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuerySymbol {
+    pub name: String,
+    pub kind: SymbolKind,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Result<Self, QueryError> {
+        Parser { input, pos: 0 }.parse()
+    }
+
+    /// True when any clause constrains symbols, so results are symbols
+    /// rather than files.
+    pub fn targets_symbols(&self) -> bool {
+        self.clauses
+            .iter()
+            .any(|c| matches!(c.filter, Filter::Kind(_) | Filter::Name(_)))
+    }
+
+    fn matches_file(&self, file: &IndexedFile) -> bool {
+        self.clauses.iter().all(|clause| {
+            let matched = match &clause.filter {
+                Filter::Lang(lang) => file.language.eq_ignore_ascii_case(lang),
+                Filter::Path(pattern) => {
+                    let path = file.relative_path.to_string_lossy().replace('\\', "/");
+                    if pattern.contains(['*', '?']) {
+                        glob_match(pattern, &path)
+                    } else {
+                        path.to_lowercase().contains(&pattern.to_lowercase())
+                    }
+                }
+                Filter::Root(root) => file.root.as_deref() == Some(root.as_str()),
+                Filter::Text(pattern) => match pattern {
+                    Pattern::Regex(regex) => regex.is_match(&file.content),
+                    _ => pattern.locate(&file.content).is_some(),
+                },
+                Filter::Kind(_) | Filter::Name(_) => return true,
+            };
+            matched != clause.negated
+        })
+    }
+
+    fn matches_symbol(&self, symbol: &Symbol) -> bool {
+        self.clauses.iter().all(|clause| {
+            let matched = match &clause.filter {
+                Filter::Kind(kind) => symbol.kind == *kind,
+                Filter::Name(pattern) => pattern.matches(&symbol.name),
+                _ => return true,
+            };
+            matched != clause.negated
+        })
+    }
+
+    fn text_patterns(&self) -> impl Iterator<Item = &Pattern> {
+        self.clauses.iter().filter(|c| !c.negated).filter_map(|c| match &c.filter {
+            Filter::Text(pattern) => Some(pattern),
+            _ => None,
+        })
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn parse(mut self) -> Result<Query, QueryError> {
+        let mut clauses = Vec::new();
+
+        loop {
+            self.skip_whitespace();
+            if self.pos >= self.input.len() {
+                break;
+            }
+            clauses.push(self.clause()?);
+        }
+
+        Ok(Query { clauses })
+    }
+
+    fn clause(&mut self) -> Result<Clause, QueryError> {
+        let start = self.pos;
+        let negated = self.rest().starts_with('-');
+        if negated {
+            self.pos += 1;
+            if self.rest().is_empty() || self.rest().starts_with(char::is_whitespace) {
+                return Err(error("Expected a clause after '-'", start, self.pos));
+            }
+        }
+
+        // A field is a run of letters followed by `:`; anything else is a bare term
+        let field_len = self.rest().find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(self.rest().len());
+        let field = if field_len > 0 && self.rest()[field_len..].starts_with(':') {
+            let field_start = self.pos;
+            let field = &self.input[field_start..field_start + field_len];
+            self.pos += field_len + 1;
+            Some((field, field_start))
+        } else {
+            None
+        };
+
+        let value_start = self.pos;
+        let (value, quoted) = self.value()?;
+        let value_end = self.pos;
+        if value.is_empty() && !quoted {
+            return Err(error("Expected a value", start, value_end));
+        }
+
+        let filter = match field {
+            None => Filter::Text(Pattern::Contains(value)),
+            Some((field, field_start)) => match field.to_ascii_lowercase().as_str() {
+                "kind" => Filter::Kind(parse_kind(&value).ok_or_else(|| {
+                    error(
+                        &format!(
+                            "Unknown symbol kind '{}'; expected function, class, variable, constant or module",
+                            value
+                        ),
+                        value_start,
+                        value_end,
+                    )
+                })?),
+                "name" => Filter::Name(parse_pattern(&value, quoted, Pattern::Exact, value_start, value_end)?),
+                "text" => Filter::Text(parse_pattern(&value, quoted, Pattern::Contains, value_start, value_end)?),
+                "lang" => Filter::Lang(value),
+                "path" => Filter::Path(value),
+                "root" => Filter::Root(value),
+                _ => {
+                    return Err(error(
+                        &format!("Unknown field '{}'", field),
+                        field_start,
+                        field_start + field.len(),
+                    ))
+                }
+            },
+        };
+
+        Ok(Clause {
+            negated,
+            filter,
+            start,
+            end: value_end,
+        })
+    }
+
+    /// Reads a bare value up to the next whitespace, or a double-quoted value
+    /// with `\"` and `\\` escapes.
+    fn value(&mut self) -> Result<(String, bool), QueryError> {
+        if !self.rest().starts_with('"') {
+            let len = self.rest().find(char::is_whitespace).unwrap_or(self.rest().len());
+            let value = self.rest()[..len].to_string();
+            self.pos += len;
+            return Ok((value, false));
+        }
+
+        let quote = self.pos;
+        self.pos += 1;
+        let mut value = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += offset + 1;
+                    if !self.rest().is_empty() && !self.rest().starts_with(char::is_whitespace) {
+                        return Err(error("Expected whitespace after closing quote", self.pos, self.pos + 1));
+                    }
+                    return Ok((value, true));
+                }
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => value.push(escaped),
+                    None => break,
+                },
+                _ => value.push(c),
+            }
+        }
+
+        Err(error("Unterminated quoted value", quote, self.input.len()))
+    }
+
+    fn rest(&self) -> &str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.input.len() - trimmed.len();
+    }
+}
+
+fn error(message: &str, start: usize, end: usize) -> QueryError {
+    QueryError {
+        message: message.to_string(),
+        start,
+        end,
+    }
+}
+
+fn parse_kind(value: &str) -> Option<SymbolKind> {
+    match value.to_ascii_lowercase().as_str() {
+        "function" | "fn" => Some(SymbolKind::Function),
+        "class" => Some(SymbolKind::Class),
+        "variable" | "var" => Some(SymbolKind::Variable),
+        "constant" | "const" => Some(SymbolKind::Constant),
+        "module" | "mod" => Some(SymbolKind::Module),
+        _ => None,
+    }
+}
+
+/// `/regex/` and `~substring` work for every pattern field; quoted values
+/// are always taken literally with the field's default matching.
+fn parse_pattern(
+    value: &str,
+    quoted: bool,
+    default: fn(String) -> Pattern,
+    start: usize,
+    end: usize,
+) -> Result<Pattern, QueryError> {
+    if quoted {
+        return Ok(default(value.to_string()));
+    }
+
+    if let Some(body) = value.strip_prefix('/').and_then(|v| v.strip_suffix('/')) {
+        return RegexBuilder::new(body)
+            .case_insensitive(true)
+            .build()
+            .map(Pattern::Regex)
+            .map_err(|e| {
+                // Syntax errors render as a multi-line diagram; keep the summary
+                let detail = e.to_string();
+                let summary = detail.lines().last().unwrap_or_default().trim_start_matches("error: ");
+                error(&format!("Invalid regex: {}", summary), start, end)
+            });
+    }
+
+    match value.strip_prefix('~') {
+        Some("") => Err(error("Expected text after '~'", start, end)),
+        Some(rest) => Ok(Pattern::Contains(rest.to_string())),
+        None => Ok(default(value.to_string())),
+    }
+}
+
+impl ProjectIndexer {
+    /// Parses and runs `query`; see [`Query`] for the syntax.
+    pub async fn query(&self, query: &str, scope: &SearchScope) -> Result<Vec<QueryHit>, QueryError> {
+        let query = Query::parse(query)?;
+        Ok(self.run_query(&query, scope).await)
+    }
+
+    /// Runs a parsed query. Files are ranked by BM25 over the positive text
+    /// terms; symbols follow the rank of their file, then their line.
+    pub async fn run_query(&self, query: &Query, scope: &SearchScope) -> Vec<QueryHit> {
+        let files = self.files.read().await;
+        let terms = self.terms.read().await;
+
+        let mut query_terms: Vec<String> = query
+            .text_patterns()
+            .flat_map(|pattern| match pattern {
+                Pattern::Exact(value) | Pattern::Contains(value) => ranking::tokenize(value),
+                Pattern::Regex(_) => Vec::new(),
+            })
+            .collect();
+        query_terms.sort();
+        query_terms.dedup();
+
+        let bm25 = Bm25::new(terms.values());
+        let weights = bm25.weights(&query_terms);
+        let scores: HashMap<&PathBuf, f64> = terms
+            .iter()
+            .map(|(path, stats)| (path, bm25.score(stats, &weights)))
+            .collect();
+
+        let mut hits = Vec::new();
+        for file in files
+            .values()
+            .filter(|f| scope.includes(f))
+            .filter(|f| query.matches_file(f))
+        {
+            let score = scores.get(&file.path).copied().unwrap_or(0.0);
+            let hit = |symbol: Option<&Symbol>, position: Option<(u32, u32)>| QueryHit {
+                path: file.path.clone(),
+                relative_path: file.relative_path.clone(),
+                root: file.root.clone(),
+                language: file.language.clone(),
+                symbol: symbol.map(|s| QuerySymbol {
+                    name: s.name.clone(),
+                    kind: s.kind.clone(),
+                }),
+                line: position.map(|(line, _)| line),
+                column: position.map(|(_, column)| column),
+                score,
+            };
+
+            if query.targets_symbols() {
+                for symbol in file.symbols.iter().filter(|s| query.matches_symbol(s)) {
+                    hits.push(hit(Some(symbol), Some((symbol.line, symbol.column))));
+                }
+            } else {
+                let position = query.text_patterns().find_map(|p| p.locate(&file.content));
+                hits.push(hit(None, position));
+            }
+        }
+
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.relative_path.cmp(&b.relative_path))
+                .then_with(|| a.line.cmp(&b.line))
+        });
+        hits
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use crate::indexer::IndexedFile;
+
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+/// Added when the file's relative path contains the whole query.
+const PATH_BOOST: f64 = 2.0;
+/// Added per query term that names a symbol defined in the file.
+pub(crate) const SYMBOL_BOOST: f64 = 3.0;
+/// Added per query term that is part of a symbol name defined in the file.
+pub(crate) const PARTIAL_SYMBOL_BOOST: f64 = 1.0;
+
+/// Term frequencies for one indexed file.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct TermStats {
+    counts: HashMap<String, u32>,
+    length: u32,
+}
+
+impl TermStats {
+    pub(crate) fn from_text(text: &str) -> Self {
+        let mut stats = Self::default();
+        for term in tokenize(text) {
+            *stats.counts.entry(term).or_insert(0) += 1;
+            stats.length += 1;
+        }
+        stats
+    }
+
+    pub(crate) fn count(&self, term: &str) -> u32 {
+        self.counts.get(term).copied().unwrap_or(0)
+    }
+}
+
+/// Splits text into lowercase search terms. Identifiers are kept whole and
+/// also split into their snake_case and camelCase parts, so `indexFile` and
+/// `index_file` both match the query `index`.
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+
+    for word in text
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|w| !w.is_empty())
+    {
+        let parts = split_identifier(word);
+        terms.push(word.to_lowercase());
+        if parts.len() > 1 {
+            terms.extend(parts.into_iter().map(|p| p.to_lowercase()));
+        }
+    }
+
+    terms
+}
+
+fn split_identifier(word: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+
+    for segment in word.split('_').filter(|s| !s.is_empty()) {
+        let mut start = 0;
+        let mut prev_lower = false;
+        for (i, c) in segment.char_indices() {
+            if c.is_uppercase() && prev_lower {
+                parts.push(&segment[start..i]);
+                start = i;
+            }
+            prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        }
+        parts.push(&segment[start..]);
+    }
+
+    parts
+}
+
+/// BM25 scorer over a corpus of documents (files or chunks).
+pub(crate) struct Bm25<'a> {
+    corpus: Vec<&'a TermStats>,
+    avg_length: f64,
+}
+
+impl<'a> Bm25<'a> {
+    pub(crate) fn new(corpus: impl IntoIterator<Item = &'a TermStats>) -> Self {
+        let corpus: Vec<&TermStats> = corpus.into_iter().collect();
+        let total: u64 = corpus.iter().map(|s| s.length as u64).sum();
+        let avg_length = if corpus.is_empty() {
+            0.0
+        } else {
+            total as f64 / corpus.len() as f64
+        };
+
+        Self { corpus, avg_length }
+    }
+
+    fn idf(&self, term: &str) -> f64 {
+        let n = self.corpus.len() as f64;
+        let df = self.corpus.iter().filter(|s| s.count(term) > 0).count() as f64;
+        ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+    }
+
+    /// Precomputes the IDF of each term so scoring many documents stays linear.
+    pub(crate) fn weights(&self, terms: &[String]) -> Vec<(String, f64)> {
+        terms.iter().map(|t| (t.clone(), self.idf(t))).collect()
+    }
+
+    pub(crate) fn score(&self, stats: &TermStats, weights: &[(String, f64)]) -> f64 {
+        let length_norm = if self.avg_length > 0.0 {
+            stats.length as f64 / self.avg_length
+        } else {
+            1.0
+        };
+
+        weights
+            .iter()
+            .map(|(term, idf)| {
+                let tf = stats.count(term) as f64;
+                idf * (tf * (K1 + 1.0)) / (tf + K1 * (1.0 - B + B * length_norm))
+            })
+            .sum()
+    }
+}
+
+/// Path and symbol-definition boosts added on top of the BM25 score.
+pub(crate) fn boost(file: &IndexedFile, query_lower: &str, terms: &[String]) -> f64 {
+    let mut boost = 0.0;
+
+    if file
+        .relative_path
+        .to_string_lossy()
+        .to_lowercase()
+        .contains(query_lower)
+    {
+        boost += PATH_BOOST;
+    }
+
+    for term in terms {
+        let mut best = 0.0;
+        for symbol in &file.symbols {
+            let name = symbol.name.to_lowercase();
+            if &name == term {
+                best = SYMBOL_BOOST;
+                break;
+            } else if name.contains(term.as_str()) {
+                best = PARTIAL_SYMBOL_BOOST;
+            }
+        }
+        boost += best;
+    }
+
+    boost
+}
@@ -0,0 +1,248 @@
+use std::path::{Path, PathBuf};
+
+use regex::{Captures, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+
+use crate::fsutil;
+use crate::indexer::ProjectIndexer;
+use crate::workspace::SearchScope;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplaceRequest {
+    pub query: String,
+    pub replacement: String,
+    /// Treat `query` as a regex; `$1`/`${name}` in `replacement` expand capture groups.
+    #[serde(default)]
+    pub is_regex: bool,
+    #[serde(default)]
+    pub case_sensitive: bool,
+    #[serde(skip)]
+    pub scope: SearchScope,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplaceEdit {
+    /// 1-based line of the match start.
+    pub line: u32,
+    pub column: u32,
+    /// Byte range of the match in the previewed content.
+    pub start: usize,
+    pub end: usize,
+    pub original: String,
+    pub replacement: String,
+    /// Text of the line containing the match, for display.
+    pub line_text: String,
+    /// Only selected edits are written by `apply_replace`.
+    pub selected: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilePreview {
+    pub path: PathBuf,
+    pub relative_path: PathBuf,
+    /// Hex SHA-256 of the content the edits were computed against.
+    pub content_hash: String,
+    pub edits: Vec<ReplaceEdit>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplacePreview {
+    pub files: Vec<FilePreview>,
+}
+
+impl ReplacePreview {
+    pub fn total_edits(&self) -> usize {
+        self.files.iter().map(|f| f.edits.len()).sum()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplaceOutcome {
+    pub files_changed: usize,
+    pub edits_applied: usize,
+}
+
+impl ProjectIndexer {
+    /// Computes every replacement for `request` without touching the disk.
+    pub async fn preview_replace(&self, request: &ReplaceRequest) -> anyhow::Result<ReplacePreview> {
+        let regex = build_regex(request)?;
+        let mut preview = ReplacePreview::default();
+
+        for file in self.files_in(&request.scope).await {
+            let edits = compute_edits(&regex, request, &file.content);
+            if edits.is_empty() {
+                continue;
+            }
+
+            preview.files.push(FilePreview {
+                path: file.path,
+                relative_path: file.relative_path,
+                content_hash: hash_content(&file.content),
+                edits,
+            });
+        }
+
+        preview.files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        Ok(preview)
+    }
+
+    /// Writes the selected edits of `preview` and re-indexes the touched files.
+    ///
+    /// Nothing is written if any file differs from the content the preview was
+    /// computed against, or is not an indexed file inside a workspace root.
+    /// If a write fails midway, files already written are restored to their
+    /// previous content.
+    pub async fn apply_replace(&self, preview: &ReplacePreview) -> anyhow::Result<ReplaceOutcome> {
+        let mut pending = Vec::new();
+
+        for file in &preview.files {
+            let selected: Vec<&ReplaceEdit> = file.edits.iter().filter(|e| e.selected).collect();
+            if selected.is_empty() {
+                continue;
+            }
+
+            self.check_editable(&file.path).await?;
+            let original = fs::read_to_string(&file.path).await?;
+            if hash_content(&original) != file.content_hash {
+                anyhow::bail!("File {:?} changed on disk since the preview", file.path);
+            }
+
+            let updated = apply_edits(&original, &selected)?;
+            pending.push((file.path.clone(), original, updated, selected.len()));
+        }
+
+        let mut written: Vec<(&PathBuf, &String)> = Vec::new();
+        for (path, original, updated, _) in &pending {
+            if let Err(e) = write_atomic(path, updated).await {
+                for (done_path, done_original) in written {
+                    if let Err(restore_err) = write_atomic(done_path, done_original).await {
+                        eprintln!("Error restoring file {:?}: {}", done_path, restore_err);
+                    }
+                }
+                return Err(e.context(format!("Failed to write {:?}", path)));
+            }
+            written.push((path, original));
+        }
+
+        let mut outcome = ReplaceOutcome::default();
+        for (path, _, _, edits) in pending {
+            if let Err(e) = self.index_file(path.clone()).await {
+                eprintln!("Error re-indexing file {:?}: {}", path, e);
+            }
+            outcome.files_changed += 1;
+            outcome.edits_applied += edits;
+        }
+
+        Ok(outcome)
+    }
+
+    /// Previews can come from outside the process, so their paths are only
+    /// trusted when they name an indexed file that resolves inside its root.
+    async fn check_editable(&self, path: &Path) -> anyhow::Result<()> {
+        let root = self.files.read().await.get(path).and_then(|f| f.root.clone());
+        let root = match root {
+            Some(root) => self.root(&root).await?,
+            None => anyhow::bail!("File {:?} is not part of the workspace", path),
+        };
+
+        // Catches symlinks that point out of the root
+        let resolved = fs::canonicalize(path).await?;
+        let root_path = fs::canonicalize(&root.path).await?;
+        if !resolved.starts_with(&root_path) {
+            anyhow::bail!("File {:?} is outside workspace root {}", path, root.name);
+        }
+
+        Ok(())
+    }
+}
+
+fn build_regex(request: &ReplaceRequest) -> anyhow::Result<Regex> {
+    if request.query.is_empty() {
+        anyhow::bail!("Search query is empty");
+    }
+
+    let pattern = if request.is_regex {
+        request.query.clone()
+    } else {
+        regex::escape(&request.query)
+    };
+
+    Ok(RegexBuilder::new(&pattern)
+        .case_insensitive(!request.case_sensitive)
+        .multi_line(true)
+        .build()?)
+}
+
+fn compute_edits(regex: &Regex, request: &ReplaceRequest, content: &str) -> Vec<ReplaceEdit> {
+    regex
+        .captures_iter(content)
+        .filter_map(|caps| {
+            let m = caps.get(0)?;
+            if m.as_str().is_empty() {
+                return None;
+            }
+
+            let replacement = expand(request, &caps);
+            let line_start = content[..m.start()].rfind('\n').map(|i| i + 1).unwrap_or(0);
+            let line_end = content[m.start()..]
+                .find('\n')
+                .map(|i| m.start() + i)
+                .unwrap_or(content.len());
+
+            Some(ReplaceEdit {
+                line: content[..m.start()].matches('\n').count() as u32 + 1,
+                column: (m.start() - line_start) as u32,
+                start: m.start(),
+                end: m.end(),
+                original: m.as_str().to_string(),
+                replacement,
+                line_text: content[line_start..line_end].trim_end_matches('\r').to_string(),
+                selected: true,
+            })
+        })
+        .collect()
+}
+
+fn expand(request: &ReplaceRequest, caps: &Captures) -> String {
+    if request.is_regex {
+        let mut out = String::new();
+        caps.expand(&request.replacement, &mut out);
+        out
+    } else {
+        request.replacement.clone()
+    }
+}
+
+fn apply_edits(content: &str, edits: &[&ReplaceEdit]) -> anyhow::Result<String> {
+    let mut sorted = edits.to_vec();
+    sorted.sort_by_key(|e| e.start);
+
+    let mut out = String::with_capacity(content.len());
+    let mut cursor = 0;
+    for edit in sorted {
+        if edit.start < cursor || content.get(edit.start..edit.end) != Some(edit.original.as_str()) {
+            anyhow::bail!("Edit at line {} no longer matches the file", edit.line);
+        }
+        out.push_str(&content[cursor..edit.start]);
+        out.push_str(&edit.replacement);
+        cursor = edit.end;
+    }
+    out.push_str(&content[cursor..]);
+
+    Ok(out)
+}
+
+fn hash_content(content: &str) -> String {
+    Sha256::digest(content.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+async fn write_atomic(path: &Path, content: &str) -> anyhow::Result<()> {
+    let (path, content) = (path.to_path_buf(), content.to_string());
+    tokio::task::spawn_blocking(move || fsutil::write_atomic(&path, &content)).await??;
+    Ok(())
+}
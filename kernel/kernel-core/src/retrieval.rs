@@ -0,0 +1,254 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::indexer::{IndexedFile, ProjectIndexer, SymbolKind};
+use crate::language::LanguageDefinition;
+use crate::ranking::{self, Bm25, TermStats, PARTIAL_SYMBOL_BOOST, SYMBOL_BOOST};
+use crate::workspace::SearchScope;
+
+/// Chunks longer than this are split into several windows.
+const MAX_CHUNK_LINES: u32 = 80;
+/// Window size for files without any symbol boundaries.
+const WINDOW_LINES: u32 = 40;
+
+/// Words that carry no meaning in natural-language queries.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "does", "for", "how", "in", "is", "it", "of", "on", "or", "the",
+    "to", "what", "where", "which", "with",
+];
+
+/// Line range of a chunk inside its file, stored at index time.
+#[derive(Debug, Clone)]
+pub(crate) struct ChunkSpan {
+    pub start_line: u32,
+    pub end_line: u32,
+    pub symbol: Option<String>,
+    pub kind: Option<SymbolKind>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct IndexedChunk {
+    pub span: ChunkSpan,
+    pub stats: TermStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CodeChunk {
+    pub path: PathBuf,
+    pub relative_path: PathBuf,
+    pub language: String,
+    /// 1-based, inclusive line range.
+    pub start_line: u32,
+    pub end_line: u32,
+    /// Symbol that opens the chunk, if it starts at a definition.
+    pub symbol: Option<String>,
+    pub kind: Option<SymbolKind>,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkHit {
+    pub chunk: CodeChunk,
+    pub score: f64,
+}
+
+impl ProjectIndexer {
+    /// Returns the `k` chunks most relevant to `query`, ranked lexically with
+    /// BM25 so retrieval works without any embedding model or network access.
+    pub async fn retrieve_chunks(&self, query: &str, k: usize, scope: &SearchScope) -> Vec<ChunkHit> {
+        let mut terms: Vec<String> = ranking::tokenize(query)
+            .into_iter()
+            .filter(|t| !STOP_WORDS.contains(&t.as_str()))
+            .collect();
+        terms.sort();
+        terms.dedup();
+
+        if terms.is_empty() || k == 0 {
+            return Vec::new();
+        }
+
+        let files = self.files.read().await;
+        let chunks = self.chunks.read().await;
+
+        let candidates: Vec<(&IndexedFile, &IndexedChunk)> = chunks
+            .iter()
+            .filter_map(|(path, file_chunks)| files.get(path).map(|file| (file, file_chunks)))
+            .filter(|(file, _)| scope.includes(file))
+            .flat_map(|(file, file_chunks)| file_chunks.iter().map(move |c| (file, c)))
+            .collect();
+
+        let bm25 = Bm25::new(candidates.iter().map(|(_, c)| &c.stats));
+        let weights = bm25.weights(&terms);
+
+        let mut scored: Vec<(f64, &IndexedFile, &IndexedChunk)> = candidates
+            .iter()
+            .map(|(file, chunk)| {
+                let score = bm25.score(&chunk.stats, &weights) + symbol_boost(&chunk.span, &terms);
+                (score, *file, *chunk)
+            })
+            .filter(|(score, _, _)| *score > 0.0)
+            .collect();
+
+        scored.sort_by(|a, b| {
+            b.0.total_cmp(&a.0)
+                .then_with(|| a.1.relative_path.cmp(&b.1.relative_path))
+                .then_with(|| a.2.span.start_line.cmp(&b.2.span.start_line))
+        });
+
+        scored
+            .into_iter()
+            .take(k)
+            .map(|(score, file, chunk)| ChunkHit {
+                chunk: materialize(file, &chunk.span),
+                score,
+            })
+            .collect()
+    }
+}
+
+/// Splits a file along function, class and module boundaries. Comment and
+/// attribute lines directly above a definition stay with it.
+pub(crate) fn chunk_file(file: &IndexedFile, language: Option<&LanguageDefinition>) -> Vec<IndexedChunk> {
+    let lines: Vec<&str> = file.content.lines().collect();
+    let total = lines.len() as u32;
+    if total == 0 {
+        return Vec::new();
+    }
+
+    let mut boundaries: Vec<(u32, &str, SymbolKind)> = file
+        .symbols
+        .iter()
+        .filter(|s| matches!(s.kind, SymbolKind::Function | SymbolKind::Class | SymbolKind::Module))
+        // Imported symbols may point past the end of a file that has since changed
+        .filter(|s| (1..=total).contains(&s.line))
+        .map(|s| (s.line, s.name.as_str(), s.kind.clone()))
+        .collect();
+    boundaries.sort_by_key(|(line, _, _)| *line);
+    boundaries.dedup_by_key(|(line, _, _)| *line);
+
+    let mut spans = Vec::new();
+    if boundaries.is_empty() {
+        push_windows(&mut spans, 1, total, None, None, WINDOW_LINES);
+    } else {
+        let mut starts = Vec::with_capacity(boundaries.len());
+        let mut floor = 1;
+        for (line, _, _) in &boundaries {
+            let mut start = *line;
+            while start > floor
+                && lines
+                    .get((start - 2) as usize)
+                    .is_some_and(|line| is_preamble(line, language))
+            {
+                start -= 1;
+            }
+            starts.push(start);
+            floor = line + 1;
+        }
+
+        if starts[0] > 1 {
+            push_windows(&mut spans, 1, starts[0] - 1, None, None, MAX_CHUNK_LINES);
+        }
+
+        for (i, (_, name, kind)) in boundaries.iter().enumerate() {
+            let end = starts.get(i + 1).map(|s| s - 1).unwrap_or(total);
+            push_windows(&mut spans, starts[i], end, Some(name), Some(kind.clone()), MAX_CHUNK_LINES);
+        }
+    }
+
+    spans
+        .into_iter()
+        .filter_map(|mut span| {
+            while span.end_line > span.start_line && lines[(span.end_line - 1) as usize].trim().is_empty() {
+                span.end_line -= 1;
+            }
+            let text = lines[(span.start_line - 1) as usize..span.end_line as usize].join("\n");
+            if text.trim().is_empty() {
+                return None;
+            }
+            Some(IndexedChunk {
+                stats: TermStats::from_text(&text),
+                span,
+            })
+        })
+        .collect()
+}
+
+fn push_windows(
+    spans: &mut Vec<ChunkSpan>,
+    start: u32,
+    end: u32,
+    symbol: Option<&str>,
+    kind: Option<SymbolKind>,
+    size: u32,
+) {
+    let mut from = start;
+    while from <= end {
+        let to = (from + size - 1).min(end);
+        spans.push(ChunkSpan {
+            start_line: from,
+            end_line: to,
+            symbol: symbol.map(|s| s.to_string()),
+            kind: kind.clone(),
+        });
+        from = to + 1;
+    }
+}
+
+fn is_preamble(line: &str, language: Option<&LanguageDefinition>) -> bool {
+    let trimmed = line.trim_start();
+    if trimmed.starts_with("#[") || trimmed.starts_with('@') {
+        return true;
+    }
+
+    language
+        .map(|l| {
+            l.line_comments.iter().any(|c| trimmed.starts_with(c.as_str()))
+                || l.block_comment
+                    .as_ref()
+                    .map(|b| trimmed.starts_with(b.start.as_str()) || trimmed.starts_with('*'))
+                    .unwrap_or(false)
+        })
+        .unwrap_or(false)
+}
+
+fn symbol_boost(span: &ChunkSpan, terms: &[String]) -> f64 {
+    let name = match &span.symbol {
+        Some(name) => name.to_lowercase(),
+        None => return 0.0,
+    };
+
+    terms
+        .iter()
+        .map(|t| {
+            if &name == t {
+                SYMBOL_BOOST
+            } else if name.contains(t.as_str()) {
+                PARTIAL_SYMBOL_BOOST
+            } else {
+                0.0
+            }
+        })
+        .sum()
+}
+
+fn materialize(file: &IndexedFile, span: &ChunkSpan) -> CodeChunk {
+    let content = file
+        .content
+        .lines()
+        .skip((span.start_line - 1) as usize)
+        .take((span.end_line - span.start_line + 1) as usize)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    CodeChunk {
+        path: file.path.clone(),
+        relative_path: file.relative_path.clone(),
+        language: file.language.clone(),
+        start_line: span.start_line,
+        end_line: span.end_line,
+        symbol: span.symbol.clone(),
+        kind: span.kind.clone(),
+        content,
+    }
+}
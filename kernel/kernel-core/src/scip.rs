@@ -0,0 +1,547 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use tokio::fs;
+
+use crate::indexer::{ProjectIndexer, Symbol, SymbolKind};
+
+/// Scheme used for the global symbols we emit, e.g.
+/// `meacode . . . src/`main.rs`/run().`.
+const SYMBOL_SCHEME: &str = "meacode";
+const TOOL_NAME: &str = "MeaCode Studio";
+
+// Enum values from scip.proto
+const TEXT_ENCODING_UTF8: u64 = 1;
+const POSITION_UTF8: u64 = 1;
+const POSITION_UTF16: u64 = 2;
+const POSITION_UTF32: u64 = 3;
+const ROLE_DEFINITION: u64 = 0x1;
+const KIND_CLASS: u64 = 7;
+const KIND_CONSTANT: u64 = 8;
+const KIND_CONSTRUCTOR: u64 = 9;
+const KIND_ENUM: u64 = 11;
+const KIND_FIELD: u64 = 15;
+const KIND_FUNCTION: u64 = 17;
+const KIND_INTERFACE: u64 = 21;
+const KIND_METHOD: u64 = 26;
+const KIND_MODULE: u64 = 29;
+const KIND_NAMESPACE: u64 = 30;
+const KIND_PACKAGE: u64 = 35;
+const KIND_PROPERTY: u64 = 41;
+const KIND_VARIABLE: u64 = 61;
+
+/// Our language IDs and the names SCIP uses for them.
+const LANGUAGE_NAMES: &[(&str, &str)] = &[
+    ("typescript", "TypeScript"),
+    ("javascript", "JavaScript"),
+    ("rust", "Rust"),
+    ("python", "Python"),
+    ("java", "Java"),
+    ("kotlin", "Kotlin"),
+    ("csharp", "CSharp"),
+    ("go", "Go"),
+    ("cpp", "CPP"),
+    ("c", "C"),
+    ("ruby", "Ruby"),
+    ("shell", "ShellScript"),
+    ("toml", "TOML"),
+    ("json", "JSON"),
+    ("markdown", "Markdown"),
+    ("dockerfile", "Dockerfile"),
+    ("makefile", "Makefile"),
+];
+
+impl ProjectIndexer {
+    /// Exports the symbols of `root` as a protobuf-encoded SCIP index, with
+    /// one document per file and a definition occurrence per symbol.
+    pub async fn export_scip(&self, root: &str) -> anyhow::Result<Vec<u8>> {
+        let root = self.root(root).await?;
+        let files = self.files.read().await;
+
+        let mut owned: Vec<_> = files
+            .values()
+            .filter(|f| f.root.as_deref() == Some(root.name.as_str()))
+            .collect();
+        owned.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+        let mut index = Writer::default();
+        index.message(1, |metadata| {
+            metadata.message(2, |tool| {
+                tool.string(1, TOOL_NAME);
+                tool.string(2, env!("CARGO_PKG_VERSION"));
+            });
+            metadata.string(3, &project_root_uri(&root.path));
+            metadata.varint(4, TEXT_ENCODING_UTF8);
+        });
+
+        for file in owned {
+            let relative_path = document_path(&file.relative_path);
+            let language = LANGUAGE_NAMES
+                .iter()
+                .find(|(id, _)| *id == file.language)
+                .map(|(_, name)| *name)
+                .unwrap_or(&file.language);
+
+            index.message(2, |document| {
+                document.string(1, &relative_path);
+                let mut described = Vec::new();
+                for symbol in &file.symbols {
+                    let id = symbol_id(&relative_path, symbol);
+                    document.message(2, |occurrence| {
+                        let start = symbol.column as i64;
+                        let end = start + symbol.name.len() as i64;
+                        occurrence.packed(1, &[symbol.line as i64 - 1, start, end]);
+                        occurrence.string(2, &id);
+                        occurrence.varint(3, ROLE_DEFINITION);
+                    });
+                    if !described.contains(&id) {
+                        document.message(3, |info| {
+                            info.string(1, &id);
+                            info.varint(5, scip_kind(&symbol.kind));
+                            info.string(6, &symbol.name);
+                        });
+                        described.push(id);
+                    }
+                }
+                document.string(4, language);
+                document.varint(6, POSITION_UTF8);
+            });
+        }
+
+        Ok(index.buf)
+    }
+
+    /// Loads the definitions of a SCIP index into `root` without
+    /// re-extracting symbols. File contents come from each document's `text`
+    /// when present and from disk otherwise. Returns the number of files loaded.
+    pub async fn import_scip(&self, root: &str, bytes: &[u8]) -> anyhow::Result<usize> {
+        let root = self.root(root).await?;
+
+        let mut documents = Vec::new();
+        for field in Reader::new(bytes) {
+            if let (2, Value::Bytes(document)) = field? {
+                documents.push(parse_document(document)?);
+            }
+        }
+
+        let mut count = 0;
+        for document in documents {
+            let path = match root.resolve(&document.relative_path) {
+                Some(path) => path,
+                None => {
+                    eprintln!("Skipping SCIP document {:?} outside root {}", document.relative_path, root.name);
+                    continue;
+                }
+            };
+            let content = match document.text {
+                Some(text) => text,
+                None => fs::read_to_string(&path).await.unwrap_or_default(),
+            };
+            let lines: Vec<&str> = content.lines().collect();
+
+            let mut symbols: Vec<Symbol> = document
+                .definitions
+                .into_iter()
+                .filter_map(|(range, id)| {
+                    if id.starts_with("local ") {
+                        return None;
+                    }
+                    let (descriptor_name, descriptor_kind) = parse_descriptor(&id)?;
+                    let info = document.infos.get(&id);
+                    let line = *range.first()?;
+                    let character = *range.get(1)?;
+                    // Ranges from an index older than the file may point past its end
+                    let text = lines.get(usize::try_from(line).ok()?)?;
+                    let column = byte_column(text, character, document.position_encoding);
+
+                    Some(Symbol {
+                        name: info
+                            .and_then(|i| i.display_name.clone())
+                            .unwrap_or(descriptor_name),
+                        kind: info.and_then(|i| kind_from_scip(i.kind)).unwrap_or(descriptor_kind),
+                        line: u32::try_from(line).ok()? + 1,
+                        column: u32::try_from(column).ok()?,
+                    })
+                })
+                .collect();
+            symbols.sort_by_key(|s| (s.line, s.column));
+
+            let language = self.detect_language(&path, &content);
+            self.store_file(path, content, language, symbols, None).await;
+            count += 1;
+        }
+
+        Ok(count)
+    }
+}
+
+#[derive(Default)]
+struct Document {
+    relative_path: PathBuf,
+    text: Option<String>,
+    position_encoding: u64,
+    /// Range and symbol of every occurrence with the definition role.
+    definitions: Vec<(Vec<i64>, String)>,
+    infos: HashMap<String, SymbolInfo>,
+}
+
+struct SymbolInfo {
+    kind: u64,
+    display_name: Option<String>,
+}
+
+fn parse_document(bytes: &[u8]) -> anyhow::Result<Document> {
+    let mut document = Document::default();
+
+    for field in Reader::new(bytes) {
+        match field? {
+            (1, Value::Bytes(path)) => document.relative_path = PathBuf::from(utf8(path)?),
+            (2, Value::Bytes(occurrence)) => {
+                let mut range = Vec::new();
+                let mut symbol = String::new();
+                let mut roles = 0;
+                for field in Reader::new(occurrence) {
+                    match field? {
+                        (1, Value::Bytes(packed)) => range.extend(read_packed(packed)?),
+                        (1, Value::Varint(value)) => range.push(value as i32 as i64),
+                        (2, Value::Bytes(s)) => symbol = utf8(s)?,
+                        (3, Value::Varint(value)) => roles = value,
+                        _ => {}
+                    }
+                }
+                if roles & ROLE_DEFINITION != 0 && !symbol.is_empty() {
+                    document.definitions.push((range, symbol));
+                }
+            }
+            (3, Value::Bytes(info)) => {
+                let mut symbol = String::new();
+                let mut kind = 0;
+                let mut display_name = None;
+                for field in Reader::new(info) {
+                    match field? {
+                        (1, Value::Bytes(s)) => symbol = utf8(s)?,
+                        (5, Value::Varint(value)) => kind = value,
+                        (6, Value::Bytes(s)) => display_name = Some(utf8(s)?).filter(|n| !n.is_empty()),
+                        _ => {}
+                    }
+                }
+                document.infos.insert(symbol, SymbolInfo { kind, display_name });
+            }
+            (5, Value::Bytes(text)) => document.text = Some(utf8(text)?).filter(|t| !t.is_empty()),
+            (6, Value::Varint(value)) => document.position_encoding = value,
+            _ => {}
+        }
+    }
+
+    Ok(document)
+}
+
+/// Global SCIP symbol for a definition: the file path as namespaces followed
+/// by a descriptor whose suffix encodes the kind.
+fn symbol_id(relative_path: &str, symbol: &Symbol) -> String {
+    let mut id = format!("{} . . . ", SYMBOL_SCHEME);
+    for part in relative_path.split('/') {
+        id.push_str(&escape_name(part));
+        id.push('/');
+    }
+
+    let name = escape_name(&symbol.name);
+    match symbol.kind {
+        SymbolKind::Function => id.push_str(&format!("{}().", name)),
+        SymbolKind::Class => id.push_str(&format!("{}#", name)),
+        SymbolKind::Module => id.push_str(&format!("{}/", name)),
+        SymbolKind::Variable | SymbolKind::Constant => id.push_str(&format!("{}.", name)),
+    }
+    id
+}
+
+fn escape_name(name: &str) -> String {
+    if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || "_+-$".contains(c)) {
+        name.to_string()
+    } else {
+        format!("`{}`", name.replace('`', "``"))
+    }
+}
+
+/// Name and kind of the last descriptor of a global symbol, used when the
+/// index carries no `SymbolInformation` for it.
+fn parse_descriptor(id: &str) -> Option<(String, SymbolKind)> {
+    // Skip the scheme and the three package fields; `  ` escapes a space
+    let mut rest = id;
+    for _ in 0..4 {
+        let mut end = 0;
+        let bytes = rest.as_bytes();
+        while end < bytes.len() {
+            if bytes[end] == b' ' {
+                if bytes.get(end + 1) == Some(&b' ') {
+                    end += 2;
+                    continue;
+                }
+                break;
+            }
+            end += 1;
+        }
+        rest = rest.get(end + 1..)?;
+    }
+
+    let mut last = None;
+    let chars: Vec<char> = rest.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let name = if chars[i] == '`' {
+            let mut name = String::new();
+            i += 1;
+            while i < chars.len() {
+                if chars[i] == '`' {
+                    if chars.get(i + 1) == Some(&'`') {
+                        name.push('`');
+                        i += 2;
+                        continue;
+                    }
+                    i += 1;
+                    break;
+                }
+                name.push(chars[i]);
+                i += 1;
+            }
+            name
+        } else {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || "_+-$".contains(chars[i])) {
+                i += 1;
+            }
+            chars[start..i].iter().collect()
+        };
+
+        let kind = match chars.get(i) {
+            Some('/') => SymbolKind::Module,
+            Some('#') => SymbolKind::Class,
+            Some('.') => SymbolKind::Variable,
+            Some(':') | Some('!') => SymbolKind::Variable,
+            Some('(') => {
+                // Method: `name(disambiguator).`
+                while i < chars.len() && chars[i] != ')' {
+                    i += 1;
+                }
+                i += 1;
+                SymbolKind::Function
+            }
+            Some('[') => {
+                // Type parameter: `[name]`
+                while i < chars.len() && chars[i] != ']' {
+                    i += 1;
+                }
+                SymbolKind::Variable
+            }
+            _ => return last,
+        };
+        i += 1;
+        last = Some((name, kind));
+    }
+
+    last
+}
+
+fn scip_kind(kind: &SymbolKind) -> u64 {
+    match kind {
+        SymbolKind::Function => KIND_FUNCTION,
+        SymbolKind::Class => KIND_CLASS,
+        SymbolKind::Variable => KIND_VARIABLE,
+        SymbolKind::Constant => KIND_CONSTANT,
+        SymbolKind::Module => KIND_MODULE,
+    }
+}
+
+fn kind_from_scip(kind: u64) -> Option<SymbolKind> {
+    match kind {
+        KIND_FUNCTION | KIND_METHOD | KIND_CONSTRUCTOR => Some(SymbolKind::Function),
+        KIND_CLASS | KIND_ENUM | KIND_INTERFACE => Some(SymbolKind::Class),
+        KIND_VARIABLE | KIND_FIELD | KIND_PROPERTY => Some(SymbolKind::Variable),
+        KIND_CONSTANT => Some(SymbolKind::Constant),
+        KIND_MODULE | KIND_NAMESPACE | KIND_PACKAGE => Some(SymbolKind::Module),
+        _ => None,
+    }
+}
+
+/// Converts a character offset in the document's position encoding into the
+/// byte column we store.
+fn byte_column(line: &str, character: i64, encoding: u64) -> i64 {
+    if encoding != POSITION_UTF16 && encoding != POSITION_UTF32 {
+        return character;
+    }
+
+    let mut units = 0;
+    for (byte, c) in line.char_indices() {
+        if units >= character {
+            return byte as i64;
+        }
+        units += if encoding == POSITION_UTF16 { c.len_utf16() as i64 } else { 1 };
+    }
+    line.len() as i64
+}
+
+fn document_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+fn project_root_uri(path: &Path) -> String {
+    let path = path.to_string_lossy().replace('\\', "/");
+    if path.starts_with('/') {
+        format!("file://{}", path)
+    } else {
+        format!("file:///{}", path)
+    }
+}
+
+fn utf8(bytes: &[u8]) -> anyhow::Result<String> {
+    Ok(std::str::from_utf8(bytes)?.to_string())
+}
+
+/// Minimal protobuf encoder for the handful of SCIP messages we write.
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn key(&mut self, field: u32, wire_type: u8) {
+        write_varint(&mut self.buf, ((field as u64) << 3) | wire_type as u64);
+    }
+
+    fn varint(&mut self, field: u32, value: u64) {
+        if value != 0 {
+            self.key(field, 0);
+            write_varint(&mut self.buf, value);
+        }
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, 2);
+        write_varint(&mut self.buf, value.len() as u64);
+        self.buf.extend_from_slice(value);
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        if !value.is_empty() {
+            self.bytes(field, value.as_bytes());
+        }
+    }
+
+    /// Packed repeated `int32`; negative values use ten-byte varints.
+    fn packed(&mut self, field: u32, values: &[i64]) {
+        let mut packed = Vec::new();
+        for &value in values {
+            write_varint(&mut packed, value as i32 as i64 as u64);
+        }
+        self.bytes(field, &packed);
+    }
+
+    fn message(&mut self, field: u32, build: impl FnOnce(&mut Writer)) {
+        let mut inner = Writer::default();
+        build(&mut inner);
+        self.bytes(field, &inner.buf);
+    }
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Iterates over the `(field, value)` pairs of an encoded message.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf, pos: 0 }
+    }
+
+    fn read_varint(&mut self) -> anyhow::Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = *self
+                .buf
+                .get(self.pos)
+                .ok_or_else(|| anyhow::anyhow!("Truncated SCIP varint at byte {}", self.pos))?;
+            self.pos += 1;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        anyhow::bail!("Invalid SCIP varint at byte {}", self.pos)
+    }
+
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|&end| end <= self.buf.len())
+            .ok_or_else(|| anyhow::anyhow!("Truncated SCIP field at byte {}", self.pos))?;
+        let bytes = &self.buf[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    fn read_field(&mut self) -> anyhow::Result<(u32, Value<'a>)> {
+        let key = self.read_varint()?;
+        let field = (key >> 3) as u32;
+        let value = match key & 0x7 {
+            0 => Value::Varint(self.read_varint()?),
+            1 => {
+                self.take(8)?;
+                Value::Fixed
+            }
+            2 => {
+                let len = self.read_varint()? as usize;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Value::Fixed
+            }
+            wire_type => anyhow::bail!("Unsupported protobuf wire type {} in SCIP index", wire_type),
+        };
+        Ok((field, value))
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = anyhow::Result<(u32, Value<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.pos >= self.buf.len() {
+            return None;
+        }
+        let field = self.read_field();
+        if field.is_err() {
+            // Stop after the first error instead of re-reading garbage
+            self.pos = self.buf.len();
+        }
+        Some(field)
+    }
+}
+
+fn read_packed(bytes: &[u8]) -> anyhow::Result<Vec<i64>> {
+    let mut reader = Reader::new(bytes);
+    let mut values = Vec::new();
+    while reader.pos < bytes.len() {
+        values.push(reader.read_varint()? as i32 as i64);
+    }
+    Ok(values)
+}
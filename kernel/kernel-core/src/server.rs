@@ -1,20 +1,63 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
 
+use crate::indexer::ProjectIndexer;
+use crate::language::LanguageRegistry;
+use crate::license::{Feature, FeatureNotLicensed, LicenseManager};
+use crate::replace::{ReplaceOutcome, ReplacePreview, ReplaceRequest};
+use crate::retrieval::ChunkHit;
+use crate::tasks::{TaskComment, TaskFilter};
+use crate::workspace::SearchScope;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub enum KernelRequest {
     Ping,
+    /// Makes `path` the workspace later requests work on. Indexing runs in
+    /// the background, so results fill in as it progresses.
+    OpenWorkspace { path: PathBuf },
+    PreviewReplace(ReplaceRequest),
+    ApplyReplace(ReplacePreview),
+    /// Code chunks to give an AI chat as context for `query`.
+    RetrieveChunks { query: String, k: usize },
+    /// Task comments across the workspace.
+    ListTasks(TaskFilter),
+}
+
+impl KernelRequest {
+    /// Feature the license must include for this request to run.
+    pub fn required_feature(&self) -> Option<Feature> {
+        match self {
+            KernelRequest::Ping | KernelRequest::OpenWorkspace { .. } | KernelRequest::ListTasks(_) => None,
+            KernelRequest::PreviewReplace(_) | KernelRequest::ApplyReplace(_) => {
+                Some(Feature::AdvancedRefactoring)
+            }
+            KernelRequest::RetrieveChunks { .. } => Some(Feature::AIChat),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub enum KernelResponse {
     Pong,
+    WorkspaceOpened,
+    ReplacePreview(ReplacePreview),
+    Replaced(ReplaceOutcome),
+    Chunks(Vec<ChunkHit>),
+    Tasks(Vec<TaskComment>),
+    FeatureNotLicensed(FeatureNotLicensed),
+    Error(String),
 }
 
 pub struct KernelCore {
     receiver: mpsc::Receiver<KernelRequest>,
     sender: mpsc::Sender<KernelResponse>,
+    license: Arc<Mutex<LicenseManager>>,
+    indexer: Option<Arc<ProjectIndexer>>,
+    languages: LanguageRegistry,
 }
 
 impl KernelCore {
@@ -22,18 +65,89 @@ impl KernelCore {
         receiver: mpsc::Receiver<KernelRequest>,
         sender: mpsc::Sender<KernelResponse>,
     ) -> Self {
-        Self { receiver, sender }
+        Self {
+            receiver,
+            sender,
+            license: Arc::new(Mutex::new(LicenseManager::new())),
+            indexer: None,
+            languages: LanguageRegistry::builtin(),
+        }
+    }
+
+    /// Shares the application's license manager, so activations, trials and
+    /// deactivations made there apply to gated requests right away.
+    pub fn with_license(mut self, license: Arc<Mutex<LicenseManager>>) -> Self {
+        self.license = license;
+        self
+    }
+
+    /// The workspace that replace and retrieval requests work on, until an
+    /// `OpenWorkspace` request replaces it.
+    pub fn with_indexer(mut self, indexer: Arc<ProjectIndexer>) -> Self {
+        self.indexer = Some(indexer);
+        self
+    }
+
+    /// Languages for workspaces opened with `OpenWorkspace`.
+    pub fn with_languages(mut self, languages: LanguageRegistry) -> Self {
+        self.languages = languages;
+        self
     }
 
     pub async fn run(mut self) -> Result<()> {
         while let Some(msg) = self.receiver.recv().await {
-            match msg {
-                KernelRequest::Ping => {
-                    let _ = self.sender.send(KernelResponse::Pong).await;
+            if let Some(feature) = msg.required_feature() {
+                let licensed = match self.license.lock() {
+                    Ok(license) => license.require(feature),
+                    Err(poisoned) => poisoned.into_inner().require(feature),
+                };
+                if let Err(e) = licensed {
+                    let _ = self.sender.send(KernelResponse::FeatureNotLicensed(e)).await;
+                    continue;
                 }
             }
+
+            let response = self
+                .handle(msg)
+                .await
+                .unwrap_or_else(|e| KernelResponse::Error(e.to_string()));
+            let _ = self.sender.send(response).await;
         }
 
         Ok(())
     }
+
+    async fn handle(&mut self, msg: KernelRequest) -> Result<KernelResponse> {
+        Ok(match msg {
+            KernelRequest::Ping => KernelResponse::Pong,
+            KernelRequest::OpenWorkspace { path } => {
+                if !path.is_dir() {
+                    anyhow::bail!("{:?} is not a directory", path);
+                }
+                let indexer = Arc::new(ProjectIndexer::with_languages(path, self.languages.clone()));
+                let background = indexer.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = background.index_all().await {
+                        eprintln!("Error indexing workspace: {}", e);
+                    }
+                });
+                self.indexer = Some(indexer);
+                KernelResponse::WorkspaceOpened
+            }
+            KernelRequest::PreviewReplace(request) => {
+                KernelResponse::ReplacePreview(self.indexer()?.preview_replace(&request).await?)
+            }
+            KernelRequest::ApplyReplace(preview) => KernelResponse::Replaced(self.indexer()?.apply_replace(&preview).await?),
+            KernelRequest::RetrieveChunks { query, k } => {
+                KernelResponse::Chunks(self.indexer()?.retrieve_chunks(&query, k, &SearchScope::All).await)
+            }
+            KernelRequest::ListTasks(filter) => KernelResponse::Tasks(self.indexer()?.tasks(&filter).await),
+        })
+    }
+
+    fn indexer(&self) -> Result<&ProjectIndexer> {
+        self.indexer
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("No workspace is open"))
+    }
 }
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::indexer::{IndexedFile, ProjectIndexer, SymbolKind};
+use crate::workspace::SearchScope;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IndexStats {
+    pub files: usize,
+    pub bytes: u64,
+    pub lines: u64,
+    pub symbols: usize,
+    pub symbols_by_kind: HashMap<SymbolKind, usize>,
+    /// Sorted by bytes, largest first.
+    pub languages: Vec<LanguageStats>,
+    pub largest_files: Vec<FileStats>,
+    /// Only files that were indexed locally; imported files have no timing.
+    pub slowest_files: Vec<FileStats>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LanguageStats {
+    pub language: String,
+    pub files: usize,
+    pub bytes: u64,
+    pub lines: u64,
+    pub symbols: usize,
+    pub symbols_by_kind: HashMap<SymbolKind, usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileStats {
+    pub path: PathBuf,
+    pub relative_path: PathBuf,
+    pub root: Option<String>,
+    pub language: String,
+    pub bytes: u64,
+    pub lines: u64,
+    pub symbols: usize,
+    pub index_time_ms: Option<f64>,
+}
+
+impl ProjectIndexer {
+    /// Totals for the files in `scope`, broken down by language and symbol
+    /// kind, with the `top` largest and slowest-to-index files.
+    pub async fn stats(&self, scope: &SearchScope, top: usize) -> IndexStats {
+        let files = self.files.read().await;
+        let index_times = self.index_times.read().await;
+
+        let mut stats = IndexStats::default();
+        let mut languages: HashMap<&str, LanguageStats> = HashMap::new();
+        let mut all_files = Vec::new();
+
+        for file in files.values().filter(|f| scope.includes(f)) {
+            let file_stats = FileStats {
+                path: file.path.clone(),
+                relative_path: file.relative_path.clone(),
+                root: file.root.clone(),
+                language: file.language.clone(),
+                bytes: file.content.len() as u64,
+                lines: file.content.lines().count() as u64,
+                symbols: file.symbols.len(),
+                index_time_ms: index_times.get(&file.path).map(|t| t.as_secs_f64() * 1000.0),
+            };
+
+            stats.files += 1;
+            stats.bytes += file_stats.bytes;
+            stats.lines += file_stats.lines;
+            stats.symbols += file_stats.symbols;
+            count_kinds(&mut stats.symbols_by_kind, file);
+
+            let language = languages
+                .entry(file.language.as_str())
+                .or_insert_with(|| LanguageStats {
+                    language: file.language.clone(),
+                    ..Default::default()
+                });
+            language.files += 1;
+            language.bytes += file_stats.bytes;
+            language.lines += file_stats.lines;
+            language.symbols += file_stats.symbols;
+            count_kinds(&mut language.symbols_by_kind, file);
+
+            all_files.push(file_stats);
+        }
+
+        stats.languages = languages.into_values().collect();
+        stats
+            .languages
+            .sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.language.cmp(&b.language)));
+
+        all_files.sort_by(|a, b| b.bytes.cmp(&a.bytes).then_with(|| a.path.cmp(&b.path)));
+        stats.largest_files = all_files.iter().take(top).cloned().collect();
+
+        all_files.retain(|f| f.index_time_ms.is_some());
+        all_files.sort_by(|a, b| {
+            b.index_time_ms
+                .unwrap_or(0.0)
+                .total_cmp(&a.index_time_ms.unwrap_or(0.0))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        all_files.truncate(top);
+        stats.slowest_files = all_files;
+
+        stats
+    }
+}
+
+fn count_kinds(counts: &mut HashMap<SymbolKind, usize>, file: &IndexedFile) {
+    for symbol in &file.symbols {
+        *counts.entry(symbol.kind.clone()).or_default() += 1;
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use tokio::fs;
+
+use crate::indexer::{ProjectIndexer, Symbol, SymbolKind};
+
+const PROGRAM_NAME: &str = "MeaCode Studio";
+
+impl ProjectIndexer {
+    /// Exports the symbols of `root` as a sorted universal-ctags `tags` file
+    /// with paths relative to the root, as if ctags had been run inside it.
+    pub async fn export_ctags(&self, root: &str) -> anyhow::Result<String> {
+        let root = self.root(root).await?;
+        let files = self.files.read().await;
+
+        let mut entries = Vec::new();
+        for file in files.values().filter(|f| f.root.as_deref() == Some(root.name.as_str())) {
+            let path = tag_path(&file.relative_path);
+            for symbol in &file.symbols {
+                if symbol.name.contains(['\t', '\n']) {
+                    continue;
+                }
+                entries.push(format!(
+                    "{}\t{}\t{};\"\t{}\tline:{}\tlanguage:{}",
+                    symbol.name,
+                    path,
+                    symbol.line,
+                    kind_name(&symbol.kind),
+                    symbol.line,
+                    file.language
+                ));
+            }
+        }
+        entries.sort();
+
+        let mut out = String::new();
+        out.push_str("!_TAG_FILE_FORMAT\t2\t/extended format; --format=1 will not append ;\" to lines/\n");
+        out.push_str("!_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/\n");
+        out.push_str(&format!("!_TAG_PROGRAM_NAME\t{}\t//\n", PROGRAM_NAME));
+        out.push_str(&format!("!_TAG_PROGRAM_VERSION\t{}\t//\n", env!("CARGO_PKG_VERSION")));
+        for entry in entries {
+            out.push_str(&entry);
+            out.push('\n');
+        }
+
+        Ok(out)
+    }
+
+    /// Loads the symbols of a ctags file into `root` without re-extracting
+    /// them. Relative paths resolve against the root; file contents are read
+    /// from disk so search keeps working. Returns the number of files loaded.
+    pub async fn import_ctags(&self, root: &str, tags: &str) -> anyhow::Result<usize> {
+        let root = self.root(root).await?;
+
+        let mut by_file: BTreeMap<PathBuf, Vec<Tag>> = BTreeMap::new();
+        for line in tags.lines() {
+            if line.starts_with("!_") || line.trim().is_empty() {
+                continue;
+            }
+            let tag = match parse_tag(line) {
+                Some(tag) => tag,
+                None => {
+                    eprintln!("Error parsing tag line {:?}", line);
+                    continue;
+                }
+            };
+            match root.resolve(&tag.file) {
+                Some(path) => by_file.entry(path).or_default().push(tag),
+                None => eprintln!("Skipping tag for {:?} outside root {}", tag.file, root.name),
+            }
+        }
+
+        let count = by_file.len();
+        for (path, tags) in by_file {
+            let content = fs::read_to_string(&path).await.unwrap_or_default();
+            let lines: Vec<&str> = content.lines().collect();
+
+            let mut symbols: Vec<Symbol> = tags
+                .into_iter()
+                .filter_map(|tag| {
+                    let line = match tag.address {
+                        Address::Line(line) => line,
+                        Address::Pattern(pattern) => tag.line.or_else(|| find_pattern(&lines, &pattern))?,
+                    };
+                    // Tags generated before the file last changed may point past its end
+                    if line == 0 || line as usize > lines.len() {
+                        return None;
+                    }
+                    let column = lines
+                        .get((line as usize).checked_sub(1)?)
+                        .and_then(|text| text.find(&tag.name))
+                        .unwrap_or(0) as u32;
+                    Some(Symbol {
+                        name: tag.name,
+                        kind: tag.kind,
+                        line,
+                        column,
+                    })
+                })
+                .collect();
+            symbols.sort_by_key(|s| (s.line, s.column));
+
+            let language = self.detect_language(&path, &content);
+            self.store_file(path, content, language, symbols, None).await;
+        }
+
+        Ok(count)
+    }
+}
+
+struct Tag {
+    name: String,
+    file: PathBuf,
+    address: Address,
+    line: Option<u32>,
+    kind: SymbolKind,
+}
+
+enum Address {
+    Line(u32),
+    Pattern(String),
+}
+
+/// Parses `name<TAB>file<TAB>address[;"<TAB>fields...]`. The address is a
+/// line number or a `/^...$/` search pattern, which may itself contain tabs.
+fn parse_tag(line: &str) -> Option<Tag> {
+    let mut parts = line.splitn(3, '\t');
+    let name = parts.next()?.to_string();
+    let file = PathBuf::from(parts.next()?);
+    let rest = parts.next()?;
+
+    let (address, fields) = match rest.rfind(";\"") {
+        Some(end) => (&rest[..end], rest[end + 2..].trim_start_matches('\t')),
+        None => (rest, ""),
+    };
+
+    let address = match address.parse::<u32>() {
+        Ok(line) => Address::Line(line),
+        Err(_) => Address::Pattern(address.to_string()),
+    };
+
+    let mut line = None;
+    let mut kind = SymbolKind::Variable;
+    for field in fields.split('\t').filter(|f| !f.is_empty()) {
+        match field.split_once(':') {
+            Some(("line", value)) => line = value.parse().ok(),
+            Some(("kind", value)) => kind = parse_kind(value),
+            Some(_) => {}
+            None => kind = parse_kind(field),
+        }
+    }
+
+    Some(Tag {
+        name,
+        file,
+        address,
+        line,
+        kind,
+    })
+}
+
+fn kind_name(kind: &SymbolKind) -> &'static str {
+    match kind {
+        SymbolKind::Function => "function",
+        SymbolKind::Class => "class",
+        SymbolKind::Variable => "variable",
+        SymbolKind::Constant => "constant",
+        SymbolKind::Module => "module",
+    }
+}
+
+/// Maps universal-ctags kind names and their common one-letter forms.
+fn parse_kind(kind: &str) -> SymbolKind {
+    match kind {
+        "f" | "function" | "method" | "func" | "procedure" | "subroutine" => SymbolKind::Function,
+        "c" | "s" | "i" | "g" | "t" | "class" | "struct" | "interface" | "enum" | "trait" | "typedef"
+        | "type" | "union" => SymbolKind::Class,
+        "C" | "d" | "constant" | "const" | "macro" | "define" => SymbolKind::Constant,
+        "n" | "p" | "module" | "namespace" | "package" => SymbolKind::Module,
+        _ => SymbolKind::Variable,
+    }
+}
+
+fn tag_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Resolves a `/^text$/` or `?^text$?` address to a 1-based line.
+fn find_pattern(lines: &[&str], pattern: &str) -> Option<u32> {
+    let delimiter = pattern.chars().next().filter(|c| *c == '/' || *c == '?')?;
+    let body = pattern[1..].strip_suffix(delimiter).unwrap_or(&pattern[1..]);
+
+    let anchored_start = body.starts_with('^');
+    let body = body.strip_prefix('^').unwrap_or(body);
+    let anchored_end = body.ends_with('$') && !body.ends_with("\\$");
+    let body = if anchored_end { &body[..body.len() - 1] } else { body };
+    let text = body.replace(&format!("\\{}", delimiter), &delimiter.to_string()).replace("\\\\", "\\");
+
+    lines
+        .iter()
+        .position(|line| match (anchored_start, anchored_end) {
+            (true, true) => *line == text,
+            (true, false) => line.starts_with(&text),
+            (false, true) => line.ends_with(&text),
+            (false, false) => line.contains(&text),
+        })
+        .map(|idx| idx as u32 + 1)
+}
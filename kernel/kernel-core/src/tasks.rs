@@ -0,0 +1,189 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use crate::comments::extract_comments;
+use crate::indexer::ProjectIndexer;
+use crate::language::LanguageDefinition;
+use crate::workspace::SearchScope;
+
+pub const DEFAULT_TASK_TAGS: &[&str] = &["TODO", "FIXME", "HACK", "XXX"];
+
+/// A task comment such as `// TODO(tomas): handle errors`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskComment {
+    pub path: PathBuf,
+    pub relative_path: PathBuf,
+    pub root: Option<String>,
+    /// 1-based line.
+    pub line: u32,
+    /// Byte column of the tag.
+    pub column: u32,
+    pub tag: String,
+    pub assignee: Option<String>,
+    pub text: String,
+}
+
+/// Filters for `ProjectIndexer::tasks`; empty fields match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskFilter {
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub assignee: Option<String>,
+    /// Case-insensitive substring of the relative path.
+    pub path: Option<String>,
+    /// Case-insensitive substring of the task text.
+    pub text: Option<String>,
+    #[serde(skip)]
+    pub scope: SearchScope,
+}
+
+impl TaskFilter {
+    fn matches(&self, task: &TaskComment) -> bool {
+        if !self.tags.is_empty() && !self.tags.iter().any(|t| t.eq_ignore_ascii_case(&task.tag)) {
+            return false;
+        }
+        if let Some(assignee) = &self.assignee {
+            if !task
+                .assignee
+                .as_deref()
+                .map(|a| a.eq_ignore_ascii_case(assignee))
+                .unwrap_or(false)
+            {
+                return false;
+            }
+        }
+        if let Some(path) = &self.path {
+            if !task
+                .relative_path
+                .to_string_lossy()
+                .to_lowercase()
+                .contains(&path.to_lowercase())
+            {
+                return false;
+            }
+        }
+        if let Some(text) = &self.text {
+            if !task.text.to_lowercase().contains(&text.to_lowercase()) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// A task found in a single document, before it is attached to a file.
+#[derive(Debug, Clone)]
+pub struct TaskMatch {
+    pub line: u32,
+    pub column: u32,
+    pub tag: String,
+    pub assignee: Option<String>,
+    pub text: String,
+}
+
+/// Finds task tags inside the real comments of `content`.
+pub fn find_tasks<S: AsRef<str>>(content: &str, language: &LanguageDefinition, tags: &[S]) -> Vec<TaskMatch> {
+    extract_comments(content, language)
+        .into_iter()
+        .filter_map(|comment| {
+            let (offset, tag, assignee, text) = parse_task(&comment.text, tags)?;
+            Some(TaskMatch {
+                line: comment.line,
+                column: comment.column + offset as u32,
+                tag,
+                assignee,
+                text,
+            })
+        })
+        .collect()
+}
+
+/// Parses `TAG`, `TAG:` or `TAG(assignee):` followed by the task text. Tags
+/// are case-sensitive and must stand as a whole word; the earliest tag wins.
+fn parse_task<S: AsRef<str>>(comment: &str, tags: &[S]) -> Option<(usize, String, Option<String>, String)> {
+    let (start, tag) = tags
+        .iter()
+        .filter_map(|tag| find_word(comment, tag.as_ref()).map(|start| (start, tag.as_ref())))
+        .min_by_key(|(start, _)| *start)?;
+
+    let mut rest = &comment[start + tag.len()..];
+    let mut assignee = None;
+    if let Some(inner) = rest.strip_prefix('(') {
+        if let Some(close) = inner.find(')') {
+            let name = inner[..close].trim();
+            if !name.is_empty() {
+                assignee = Some(name.to_string());
+            }
+            rest = &inner[close + 1..];
+        }
+    }
+
+    let text = rest.trim_start_matches(':').trim().to_string();
+    Some((start, tag.to_string(), assignee, text))
+}
+
+fn find_word(text: &str, word: &str) -> Option<usize> {
+    if word.is_empty() {
+        return None;
+    }
+
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+    let mut search_from = 0;
+    while let Some(found) = text[search_from..].find(word) {
+        let start = search_from + found;
+        let end = start + word.len();
+        search_from = end;
+
+        let before_ok = !text[..start].chars().next_back().map(is_word_char).unwrap_or(false);
+        let after_ok = !text[end..].chars().next().map(is_word_char).unwrap_or(false);
+        if before_ok && after_ok {
+            return Some(start);
+        }
+    }
+
+    None
+}
+
+impl ProjectIndexer {
+    pub async fn task_tags(&self) -> Vec<String> {
+        self.task_tags.read().await.clone()
+    }
+
+    /// Replaces the recognised task tags; defaults to `DEFAULT_TASK_TAGS`.
+    pub async fn set_task_tags(&self, tags: Vec<String>) {
+        *self.task_tags.write().await = tags;
+    }
+
+    /// Workspace-wide task list, sorted by path and line.
+    pub async fn tasks(&self, filter: &TaskFilter) -> Vec<TaskComment> {
+        let tags = self.task_tags().await;
+        let mut tasks = Vec::new();
+
+        for file in self.files_in(&filter.scope).await {
+            let language = match self.languages().get(&file.language) {
+                Some(language) => language,
+                None => continue,
+            };
+
+            for task in find_tasks(&file.content, language, &tags) {
+                let task = TaskComment {
+                    path: file.path.clone(),
+                    relative_path: file.relative_path.clone(),
+                    root: file.root.clone(),
+                    line: task.line,
+                    column: task.column,
+                    tag: task.tag,
+                    assignee: task.assignee,
+                    text: task.text,
+                };
+                if filter.matches(&task) {
+                    tasks.push(task);
+                }
+            }
+        }
+
+        tasks.sort_by(|a, b| a.relative_path.cmp(&b.relative_path).then(a.line.cmp(&b.line)));
+        tasks
+    }
+}
@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use crate::indexer::IndexedFile;
+
+/// Name-based ignore rules applied while walking a workspace root.
+///
+/// Patterns without a `/` match against a single file or directory name and
+/// support `*` and `?`. Patterns containing a `/` match against the path
+/// relative to the root.
+#[derive(Debug, Clone)]
+pub struct IgnoreRules {
+    pub skip_hidden: bool,
+    pub patterns: Vec<String>,
+    /// Only index files tracked by git. Roots outside a repository are
+    /// walked as usual.
+    pub tracked_only: bool,
+}
+
+impl IgnoreRules {
+    pub fn none() -> Self {
+        Self {
+            skip_hidden: false,
+            patterns: Vec::new(),
+            tracked_only: false,
+        }
+    }
+
+    pub fn only_tracked(mut self) -> Self {
+        self.tracked_only = true;
+        self
+    }
+
+    pub fn with_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.patterns.push(pattern.into());
+        self
+    }
+
+    pub fn is_ignored(&self, relative_path: &Path) -> bool {
+        let name = match relative_path.file_name().and_then(|n| n.to_str()) {
+            Some(name) => name,
+            None => return false,
+        };
+
+        if self.skip_hidden && name.starts_with('.') {
+            return true;
+        }
+
+        let relative = relative_path.to_string_lossy().replace('\\', "/");
+        self.patterns.iter().any(|pattern| {
+            if pattern.contains('/') {
+                glob_match(pattern.trim_start_matches('/'), &relative)
+            } else {
+                glob_match(pattern, name)
+            }
+        })
+    }
+}
+
+impl IgnoreRules {
+    /// Like `is_ignored`, but also checks every ancestor directory, for
+    /// paths that were not reached by a walk that skips ignored directories.
+    pub fn is_ignored_within(&self, relative_path: &Path) -> bool {
+        relative_path
+            .ancestors()
+            .filter(|a| !a.as_os_str().is_empty())
+            .any(|a| self.is_ignored(a))
+    }
+}
+
+impl Default for IgnoreRules {
+    fn default() -> Self {
+        Self {
+            skip_hidden: true,
+            patterns: ["node_modules", "target", ".git", "dist", "build"]
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            tracked_only: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WorkspaceRoot {
+    pub name: String,
+    pub path: PathBuf,
+    pub ignore: IgnoreRules,
+}
+
+impl WorkspaceRoot {
+    pub fn contains(&self, path: &Path) -> bool {
+        path.starts_with(&self.path)
+    }
+
+    pub fn relative_path(&self, path: &Path) -> Option<PathBuf> {
+        path.strip_prefix(&self.path).ok().map(Path::to_path_buf)
+    }
+
+    /// Joins a path taken from outside input, such as an imported index,
+    /// onto the root. Absolute paths and `..` components are rejected so the
+    /// result never leaves the root.
+    pub fn resolve(&self, relative: &Path) -> Option<PathBuf> {
+        let stays_inside = relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+        if !stays_inside || relative.as_os_str().is_empty() {
+            return None;
+        }
+        Some(self.path.join(relative))
+    }
+}
+
+/// Restricts an operation to every root, a single root by name, or an
+/// explicit set of files such as those from `ProjectIndexer::git_scope`.
+#[derive(Debug, Clone, Default)]
+pub enum SearchScope {
+    #[default]
+    All,
+    Root(String),
+    Files(Arc<HashSet<PathBuf>>),
+}
+
+impl SearchScope {
+    pub fn includes(&self, file: &IndexedFile) -> bool {
+        match self {
+            SearchScope::All => true,
+            SearchScope::Root(name) => file.root.as_deref() == Some(name.as_str()),
+            SearchScope::Files(paths) => paths.contains(&file.path),
+        }
+    }
+}
+
+/// Minimal glob matching supporting `*` (any run of characters) and `?`.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,233 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use ed25519_dalek::SigningKey;
+use kernel_core::license::sign_license;
+use kernel_core::{
+    Feature, KernelCore, KernelRequest, KernelResponse, LicenseManager, LicensePayload, LicenseTier, ProjectIndexer,
+    ReplaceRequest,
+};
+use tokio::sync::mpsc;
+
+const NOW: u64 = 1_700_000_000;
+const DAY: u64 = 24 * 60 * 60;
+
+const FREE: &[Feature] = &[Feature::BasicEditor, Feature::FileExplorer, Feature::Terminal, Feature::BasicLsp];
+
+fn signing_key() -> SigningKey {
+    SigningKey::from_bytes(&[7; 32])
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("meacode-gating-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn manager(dir: &Path, clock: Arc<AtomicU64>) -> LicenseManager {
+    LicenseManager::new()
+        .with_public_key(signing_key().verifying_key())
+        .with_fingerprint("test-machine".to_string())
+        .with_clock(Arc::new(move || clock.load(Ordering::SeqCst)))
+        .with_storage(dir.join("license.json"))
+        .with_trial_storage(dir.join("trial.json"))
+        .with_trial_marker(dir.join("trial.marker"))
+}
+
+fn premium_license(features: &[Feature]) -> String {
+    let payload = LicensePayload {
+        license_id: "lic-1".to_string(),
+        licensee: "Test".to_string(),
+        tier: LicenseTier::Premium,
+        features: features.iter().map(|f| (*f).into()).collect(),
+        quotas: Default::default(),
+        issued_at: NOW,
+        expires_at: Some(NOW + 365 * DAY),
+        seats: None,
+        features_digest: String::new(),
+    };
+    serde_json::to_string(&sign_license(payload, &signing_key())).unwrap()
+}
+
+fn assert_features(manager: &LicenseManager, allowed: &[Feature]) {
+    for feature in Feature::ALL {
+        let expected = allowed.contains(feature);
+        assert_eq!(manager.has_feature(*feature), expected, "{} on {:?}", feature, manager.get_license_info().tier);
+        assert_eq!(manager.require(*feature).is_ok(), expected, "{} on {:?}", feature, manager.get_license_info().tier);
+    }
+}
+
+#[test]
+fn free_tier_only_has_free_features() {
+    let dir = temp_dir("free");
+    let mut manager = manager(&dir, Arc::new(AtomicU64::new(NOW)));
+    assert!(!manager.load().unwrap());
+
+    assert_eq!(manager.get_license_info().tier, LicenseTier::Free);
+    assert_features(&manager, FREE);
+
+    let denied = manager.require(Feature::AIChat).unwrap_err();
+    assert_eq!(denied.feature, Feature::AIChat);
+    assert_eq!(denied.tier, LicenseTier::Free);
+}
+
+#[test]
+fn trial_has_every_feature_until_it_ends() {
+    let dir = temp_dir("trial");
+    let clock = Arc::new(AtomicU64::new(NOW));
+    let mut manager = manager(&dir, clock.clone());
+    manager.start_trial().unwrap();
+
+    assert_eq!(manager.get_license_info().tier, LicenseTier::Trial);
+    assert_features(&manager, Feature::ALL);
+
+    clock.store(NOW + 30 * DAY, Ordering::SeqCst);
+    assert_features(&manager, FREE);
+}
+
+#[test]
+fn premium_has_every_feature_until_it_expires() {
+    let dir = temp_dir("premium");
+    let clock = Arc::new(AtomicU64::new(NOW));
+    let mut manager = manager(&dir, clock.clone());
+    manager.activate_license(&premium_license(&[Feature::AIChat])).unwrap();
+
+    assert_eq!(manager.get_license_info().tier, LicenseTier::Premium);
+    assert_features(&manager, Feature::ALL);
+
+    // Survives a restart
+    let mut restored = self::manager(&dir, clock.clone());
+    assert!(restored.load().unwrap());
+    assert_features(&restored, Feature::ALL);
+
+    // Past expiry and the grace period
+    clock.store(NOW + 400 * DAY, Ordering::SeqCst);
+    assert_features(&restored, FREE);
+}
+
+struct Kernel {
+    requests: mpsc::Sender<KernelRequest>,
+    responses: mpsc::Receiver<KernelResponse>,
+}
+
+impl Kernel {
+    fn spawn(license: Arc<Mutex<LicenseManager>>, indexer: Option<Arc<ProjectIndexer>>) -> Self {
+        let (requests, request_rx) = mpsc::channel(8);
+        let (response_tx, responses) = mpsc::channel(8);
+        let mut kernel = KernelCore::new(request_rx, response_tx).with_license(license);
+        if let Some(indexer) = indexer {
+            kernel = kernel.with_indexer(indexer);
+        }
+        tokio::spawn(kernel.run());
+        Self { requests, responses }
+    }
+
+    async fn request(&mut self, request: KernelRequest) -> KernelResponse {
+        self.requests.send(request).await.unwrap();
+        self.responses.recv().await.unwrap()
+    }
+}
+
+fn replace_request() -> KernelRequest {
+    KernelRequest::PreviewReplace(ReplaceRequest {
+        query: "old_name".to_string(),
+        replacement: "new_name".to_string(),
+        is_regex: false,
+        case_sensitive: true,
+        scope: Default::default(),
+    })
+}
+
+fn chunks_request() -> KernelRequest {
+    KernelRequest::RetrieveChunks {
+        query: "old_name".to_string(),
+        k: 5,
+    }
+}
+
+fn workspace(dir: &Path) -> PathBuf {
+    let workspace = dir.join("workspace");
+    std::fs::create_dir_all(&workspace).unwrap();
+    std::fs::write(workspace.join("lib.rs"), "fn old_name() {}\n").unwrap();
+    workspace
+}
+
+async fn indexed_workspace(dir: &Path) -> Arc<ProjectIndexer> {
+    let indexer = Arc::new(ProjectIndexer::new(workspace(dir)));
+    indexer.index_all().await.unwrap();
+    indexer
+}
+
+fn not_licensed(response: &KernelResponse, feature: Feature) -> bool {
+    matches!(response, KernelResponse::FeatureNotLicensed(e) if e.feature == feature)
+}
+
+#[tokio::test]
+async fn kernel_gates_premium_requests_by_tier() {
+    let dir = temp_dir("kernel");
+    let clock = Arc::new(AtomicU64::new(NOW));
+    let license = Arc::new(Mutex::new(manager(&dir, clock.clone())));
+    let mut kernel = Kernel::spawn(license.clone(), Some(indexed_workspace(&dir).await));
+
+    // Free: only ungated requests run
+    assert!(matches!(kernel.request(KernelRequest::Ping).await, KernelResponse::Pong));
+    assert!(not_licensed(&kernel.request(replace_request()).await, Feature::AdvancedRefactoring));
+    assert!(not_licensed(&kernel.request(chunks_request()).await, Feature::AIChat));
+
+    // Trial, started on the shared manager after the kernel is running
+    license.lock().unwrap().start_trial().unwrap();
+    match kernel.request(replace_request()).await {
+        KernelResponse::ReplacePreview(preview) => assert_eq!(preview.total_edits(), 1),
+        other => panic!("unexpected response {:?}", other),
+    }
+    match kernel.request(chunks_request()).await {
+        KernelResponse::Chunks(chunks) => assert!(!chunks.is_empty()),
+        other => panic!("unexpected response {:?}", other),
+    }
+
+    // Premium, replacing the trial
+    license
+        .lock()
+        .unwrap()
+        .activate_license(&premium_license(&[Feature::AIChat]))
+        .unwrap();
+    assert!(matches!(kernel.request(replace_request()).await, KernelResponse::ReplacePreview(_)));
+    assert!(matches!(kernel.request(chunks_request()).await, KernelResponse::Chunks(_)));
+
+    // Back to free once the license ends
+    clock.store(NOW + 400 * DAY, Ordering::SeqCst);
+    assert!(not_licensed(&kernel.request(replace_request()).await, Feature::AdvancedRefactoring));
+    assert!(not_licensed(&kernel.request(chunks_request()).await, Feature::AIChat));
+    assert!(matches!(kernel.request(KernelRequest::Ping).await, KernelResponse::Pong));
+}
+
+#[tokio::test]
+async fn gated_requests_run_on_a_workspace_opened_through_the_kernel() {
+    let dir = temp_dir("open");
+    let license = Arc::new(Mutex::new(manager(&dir, Arc::new(AtomicU64::new(NOW)))));
+    license.lock().unwrap().start_trial().unwrap();
+    let mut kernel = Kernel::spawn(license, None);
+
+    match kernel.request(replace_request()).await {
+        KernelResponse::Error(message) => assert_eq!(message, "No workspace is open"),
+        other => panic!("unexpected response {:?}", other),
+    }
+
+    let missing = KernelRequest::OpenWorkspace { path: dir.join("missing") };
+    assert!(matches!(kernel.request(missing).await, KernelResponse::Error(_)));
+
+    let open = KernelRequest::OpenWorkspace { path: workspace(&dir) };
+    assert!(matches!(kernel.request(open).await, KernelResponse::WorkspaceOpened));
+
+    // Indexing runs in the background
+    for _ in 0..100 {
+        match kernel.request(replace_request()).await {
+            KernelResponse::ReplacePreview(preview) if preview.total_edits() == 1 => return,
+            KernelResponse::ReplacePreview(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            other => panic!("unexpected response {:?}", other),
+        }
+    }
+    panic!("workspace was never indexed");
+}
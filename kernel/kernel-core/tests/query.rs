@@ -0,0 +1,128 @@
+use std::path::{Path, PathBuf};
+
+use kernel_core::query::{Filter, Pattern};
+use kernel_core::{ProjectIndexer, Query, QueryError, SearchScope, SymbolKind};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("meacode-query-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, content: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+fn parse_error(input: &str) -> QueryError {
+    Query::parse(input).unwrap_err()
+}
+
+#[test]
+fn parses_fields_negation_and_bare_terms() {
+    let query = Query::parse("kind:fn  -lang:rust path:src/*.ts root:app index").unwrap();
+
+    let clauses: Vec<_> = query.clauses.iter().map(|c| (c.negated, c.start, c.end)).collect();
+    assert_eq!(clauses, vec![(false, 0, 7), (true, 9, 19), (false, 20, 33), (false, 34, 42), (false, 43, 48)]);
+
+    assert!(matches!(query.clauses[0].filter, Filter::Kind(SymbolKind::Function)));
+    assert!(matches!(&query.clauses[1].filter, Filter::Lang(lang) if lang == "rust"));
+    assert!(matches!(&query.clauses[2].filter, Filter::Path(path) if path == "src/*.ts"));
+    assert!(matches!(&query.clauses[3].filter, Filter::Root(root) if root == "app"));
+    assert!(matches!(&query.clauses[4].filter, Filter::Text(Pattern::Contains(text)) if text == "index"));
+    assert!(query.targets_symbols());
+}
+
+#[test]
+fn parses_name_and_text_patterns() {
+    let query = Query::parse("name:run name:~Index name:/^get_/ text:TODO text:/fo+/").unwrap();
+    let filters: Vec<_> = query.clauses.iter().map(|c| &c.filter).collect();
+
+    assert!(matches!(filters[0], Filter::Name(Pattern::Exact(name)) if name == "run"));
+    assert!(matches!(filters[1], Filter::Name(Pattern::Contains(name)) if name == "Index"));
+    assert!(matches!(filters[2], Filter::Name(Pattern::Regex(regex)) if regex.is_match("GET_value")));
+    assert!(matches!(filters[3], Filter::Text(Pattern::Contains(text)) if text == "TODO"));
+    assert!(matches!(filters[4], Filter::Text(Pattern::Regex(regex)) if regex.is_match("foo")));
+}
+
+#[test]
+fn quoted_values_keep_spaces_and_are_taken_literally() {
+    let query = Query::parse(r#"text:"fn main()" name:"/not a regex/" "say \"hi\" \\" text:"""#).unwrap();
+    let filters: Vec<_> = query.clauses.iter().map(|c| &c.filter).collect();
+
+    assert!(matches!(filters[0], Filter::Text(Pattern::Contains(text)) if text == "fn main()"));
+    assert!(matches!(filters[1], Filter::Name(Pattern::Exact(name)) if name == "/not a regex/"));
+    assert!(matches!(filters[2], Filter::Text(Pattern::Contains(text)) if text == r#"say "hi" \"#));
+    assert!(matches!(filters[3], Filter::Text(Pattern::Contains(text)) if text.is_empty()));
+}
+
+#[test]
+fn fields_are_case_insensitive_and_terms_need_a_letter_run_before_the_colon() {
+    let query = Query::parse("KIND:Class Lang:rust 1:2 :x").unwrap();
+
+    assert!(matches!(query.clauses[0].filter, Filter::Kind(SymbolKind::Class)));
+    assert!(matches!(&query.clauses[1].filter, Filter::Lang(lang) if lang == "rust"));
+    assert!(matches!(&query.clauses[2].filter, Filter::Text(Pattern::Contains(text)) if text == "1:2"));
+    assert!(matches!(&query.clauses[3].filter, Filter::Text(Pattern::Contains(text)) if text == ":x"));
+    assert!(query.targets_symbols());
+}
+
+#[test]
+fn an_empty_query_has_no_clauses() {
+    assert!(Query::parse("   ").unwrap().clauses.is_empty());
+}
+
+#[test]
+fn reports_errors_at_their_byte_range() {
+    let cases = [
+        ("lang:rust color:red", "Unknown field 'color'", 10, 15),
+        ("kind:struct", "Unknown symbol kind 'struct'", 5, 11),
+        ("name:", "Expected a value", 0, 5),
+        ("a - b", "Expected a clause after '-'", 2, 3),
+        ("text:\"open", "Unterminated quoted value", 5, 10),
+        ("\"a\"b", "Expected whitespace after closing quote", 3, 4),
+        ("name:~", "Expected text after '~'", 5, 6),
+        ("text:/(/", "Invalid regex", 5, 8),
+    ];
+
+    for (input, message, start, end) in cases {
+        let error = parse_error(input);
+        assert!(error.message.starts_with(message), "{:?}: {}", input, error.message);
+        assert_eq!((error.start, error.end), (start, end), "{:?}", input);
+    }
+
+    assert_eq!(parse_error("kind:struct").to_string().lines().count(), 1);
+    assert!(parse_error("lang:rust color:red").to_string().ends_with("at column 11"));
+}
+
+#[tokio::test]
+async fn runs_symbol_and_file_queries() {
+    let dir = temp_dir("run");
+    write(&dir.join("src/main.rs"), "fn main() {\n    run_index();\n}\n\nfn run_index() {}\n");
+    write(&dir.join("src/util.py"), "def run_index():\n    pass\n");
+    write(&dir.join("README.md"), "Call run_index to build the index.\n");
+
+    let indexer = ProjectIndexer::new(dir.clone());
+    indexer.index_all().await.unwrap();
+
+    let symbols: Vec<_> = indexer
+        .query("kind:function name:~index -lang:python", &SearchScope::All)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|hit| (hit.relative_path, hit.symbol.unwrap().name, hit.line))
+        .collect();
+    assert_eq!(symbols, vec![(PathBuf::from("src/main.rs"), "run_index".to_string(), Some(5))]);
+
+    let files: Vec<_> = indexer
+        .query("\"build the\" path:*.md", &SearchScope::All)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|hit| (hit.relative_path, hit.line, hit.column))
+        .collect();
+    assert_eq!(files, vec![(PathBuf::from("README.md"), Some(1), Some(18))]);
+
+    assert!(indexer.query("kind:", &SearchScope::All).await.is_err());
+}
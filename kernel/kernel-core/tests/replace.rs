@@ -0,0 +1,109 @@
+use std::path::{Path, PathBuf};
+
+use kernel_core::{FilePreview, ProjectIndexer, ReplaceEdit, ReplacePreview, ReplaceRequest};
+use sha2::{Digest, Sha256};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("meacode-replace-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn request() -> ReplaceRequest {
+    ReplaceRequest {
+        query: "old_name".to_string(),
+        replacement: "new_name".to_string(),
+        is_regex: false,
+        case_sensitive: true,
+        scope: Default::default(),
+    }
+}
+
+/// A preview as a caller could forge it, with a correct hash for `path`.
+fn forged_preview(path: &Path) -> ReplacePreview {
+    let content = std::fs::read_to_string(path).unwrap();
+    let start = content.find("old_name").unwrap();
+    ReplacePreview {
+        files: vec![FilePreview {
+            path: path.to_path_buf(),
+            relative_path: PathBuf::from("secret.txt"),
+            content_hash: Sha256::digest(content.as_bytes())
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect(),
+            edits: vec![ReplaceEdit {
+                line: 1,
+                column: start as u32,
+                start,
+                end: start + "old_name".len(),
+                original: "old_name".to_string(),
+                replacement: "new_name".to_string(),
+                line_text: content.lines().next().unwrap().to_string(),
+                selected: true,
+            }],
+        }],
+    }
+}
+
+async fn workspace(dir: &Path) -> ProjectIndexer {
+    let workspace = dir.join("workspace");
+    std::fs::create_dir_all(&workspace).unwrap();
+    std::fs::write(workspace.join("lib.rs"), "fn old_name() {}\n").unwrap();
+    std::fs::write(dir.join("secret.txt"), "old_name\n").unwrap();
+
+    let indexer = ProjectIndexer::new(workspace);
+    indexer.index_all().await.unwrap();
+    indexer
+}
+
+#[tokio::test]
+async fn applies_a_preview_of_workspace_files() {
+    let dir = temp_dir("apply");
+    let indexer = workspace(&dir).await;
+
+    let preview = indexer.preview_replace(&request()).await.unwrap();
+    let outcome = indexer.apply_replace(&preview).await.unwrap();
+
+    assert_eq!(outcome.files_changed, 1);
+    assert_eq!(std::fs::read_to_string(dir.join("workspace/lib.rs")).unwrap(), "fn new_name() {}\n");
+}
+
+#[tokio::test]
+async fn rejects_a_preview_pointing_outside_the_workspace() {
+    let dir = temp_dir("outside");
+    let indexer = workspace(&dir).await;
+
+    for path in [dir.join("secret.txt"), dir.join("workspace/../secret.txt")] {
+        let error = indexer.apply_replace(&forged_preview(&path)).await.unwrap_err();
+        assert!(error.to_string().contains("not part of the workspace"), "{}", error);
+    }
+    assert_eq!(std::fs::read_to_string(dir.join("secret.txt")).unwrap(), "old_name\n");
+}
+
+#[tokio::test]
+async fn rejects_the_whole_preview_when_one_file_is_outside() {
+    let dir = temp_dir("mixed");
+    let indexer = workspace(&dir).await;
+
+    let mut preview = indexer.preview_replace(&request()).await.unwrap();
+    preview.files.extend(forged_preview(&dir.join("secret.txt")).files);
+
+    assert!(indexer.apply_replace(&preview).await.is_err());
+    assert_eq!(std::fs::read_to_string(dir.join("workspace/lib.rs")).unwrap(), "fn old_name() {}\n");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn rejects_an_indexed_symlink_to_a_file_outside() {
+    let dir = temp_dir("symlink");
+    let indexer = workspace(&dir).await;
+    let link = dir.join("workspace/link.txt");
+    std::os::unix::fs::symlink(dir.join("secret.txt"), &link).unwrap();
+    indexer.index_file(link.clone()).await.unwrap();
+
+    let error = indexer.apply_replace(&forged_preview(&link)).await.unwrap_err();
+
+    assert!(error.to_string().contains("outside workspace root"), "{}", error);
+    assert_eq!(std::fs::read_to_string(dir.join("secret.txt")).unwrap(), "old_name\n");
+}
@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+
+use kernel_core::{ProjectIndexer, SearchScope, SymbolKind};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("meacode-export-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, content: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+/// A root with symbols of several kinds in a nested directory.
+async fn indexed_workspace(dir: &Path) -> ProjectIndexer {
+    write(
+        &dir.join("src/main.rs"),
+        "const LIMIT: u32 = 10;\n\nstruct Config {\n    name: String,\n}\n\nfn main() {\n    run();\n}\n\nfn run() {}\n",
+    );
+    write(
+        &dir.join("tools/build.py"),
+        "class Builder:\n    def build(self):\n        pass\n\ndef main():\n    Builder().build()\n",
+    );
+
+    let indexer = ProjectIndexer::new(dir.to_path_buf());
+    indexer.index_all().await.unwrap();
+    indexer
+}
+
+async fn root_name(indexer: &ProjectIndexer) -> String {
+    indexer.roots().await[0].name.clone()
+}
+
+/// `(relative path, name, kind, line, column)` of every symbol, sorted.
+async fn symbols(indexer: &ProjectIndexer) -> Vec<(PathBuf, String, SymbolKind, u32, u32)> {
+    let mut symbols: Vec<_> = indexer
+        .files_in(&SearchScope::All)
+        .await
+        .into_iter()
+        .flat_map(|f| {
+            let path = f.relative_path;
+            f.symbols
+                .into_iter()
+                .map(move |s| (path.clone(), s.name, s.kind, s.line, s.column))
+        })
+        .collect();
+    symbols.sort_by(|a, b| (&a.0, a.3, a.4).cmp(&(&b.0, b.3, b.4)));
+    symbols
+}
+
+#[tokio::test]
+async fn ctags_export_round_trips_through_import() {
+    let dir = temp_dir("ctags");
+    let source = indexed_workspace(&dir).await;
+    let expected = symbols(&source).await;
+    assert!(expected.len() >= 5, "{:?}", expected);
+
+    let tags = source.export_ctags(&root_name(&source).await).await.unwrap();
+    assert!(tags.starts_with("!_TAG_FILE_FORMAT\t2\t"));
+    assert!(tags.lines().any(|l| l.starts_with("run\tsrc/main.rs\t11;\"\tfunction\t")), "{}", tags);
+
+    let target = ProjectIndexer::new(dir.clone());
+    assert_eq!(target.import_ctags(&root_name(&target).await, &tags).await.unwrap(), 2);
+
+    assert_eq!(symbols(&target).await, expected);
+    assert_eq!(target.find_symbols("run").await.len(), 1);
+}
+
+#[tokio::test]
+async fn scip_export_round_trips_through_import() {
+    let dir = temp_dir("scip");
+    let source = indexed_workspace(&dir).await;
+    let expected = symbols(&source).await;
+
+    let index = source.export_scip(&root_name(&source).await).await.unwrap();
+
+    let target = ProjectIndexer::new(dir.clone());
+    assert_eq!(target.import_scip(&root_name(&target).await, &index).await.unwrap(), 2);
+
+    assert_eq!(symbols(&target).await, expected);
+    let file = target.files_in(&SearchScope::All).await.into_iter().find(|f| f.language == "python");
+    assert_eq!(file.unwrap().relative_path, PathBuf::from("tools/build.py"));
+}
+
+#[tokio::test]
+async fn ctags_import_resolves_patterns_and_skips_paths_outside_the_root() {
+    let dir = temp_dir("ctags-import");
+    let workspace = dir.join("workspace");
+    write(&workspace.join("lib.c"), "#define MAX 4\n\nint\tadd(int a, int b) {\n    return a + b;\n}\n");
+    write(&dir.join("outside.c"), "int secret(void) { return 0; }\n");
+
+    let tags = concat!(
+        "!_TAG_FILE_SORTED\t1\t/0=unsorted, 1=sorted, 2=foldcase/\n",
+        "MAX\tlib.c\t/^#define MAX 4$/;\"\td\n",
+        "add\tlib.c\t/^int\tadd(int a, int b) {$/;\"\tkind:function\n",
+        "gone\tlib.c\t40;\"\tf\n",
+        "secret\t../outside.c\t1;\"\tf\n",
+    );
+
+    let indexer = ProjectIndexer::new(workspace.clone());
+    assert_eq!(indexer.import_ctags(&root_name(&indexer).await, tags).await.unwrap(), 1);
+
+    assert_eq!(
+        symbols(&indexer).await,
+        vec![
+            (PathBuf::from("lib.c"), "MAX".to_string(), SymbolKind::Constant, 1, 8),
+            (PathBuf::from("lib.c"), "add".to_string(), SymbolKind::Function, 3, 4),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn scip_import_rejects_a_truncated_index() {
+    let dir = temp_dir("scip-truncated");
+    let source = indexed_workspace(&dir).await;
+    let index = source.export_scip(&root_name(&source).await).await.unwrap();
+
+    let target = ProjectIndexer::new(dir.clone());
+    let truncated = &index[..index.len() - 3];
+
+    assert!(target.import_scip(&root_name(&target).await, truncated).await.is_err());
+    assert!(symbols(&target).await.is_empty());
+}
@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use kernel_core::{KernelCore, KernelRequest, KernelResponse, ProjectIndexer, TaskComment, TaskFilter};
+use tokio::sync::mpsc;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("meacode-tasks-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+async fn list_tasks(filter: TaskFilter) -> Vec<TaskComment> {
+    let dir = temp_dir("list");
+    std::fs::write(
+        dir.join("main.rs"),
+        "// TODO(ana): handle errors\nfn main() {\n    let s = \"TODO: not a task\";\n    /* FIXME broken */\n}\n",
+    )
+    .unwrap();
+    std::fs::write(dir.join("app.py"), "x = 'FIXME'  # HACK: quick fix\n").unwrap();
+    let indexer = std::sync::Arc::new(ProjectIndexer::new(dir));
+    indexer.index_all().await.unwrap();
+
+    let (requests, request_rx) = mpsc::channel(1);
+    let (response_tx, mut responses) = mpsc::channel(1);
+    tokio::spawn(KernelCore::new(request_rx, response_tx).with_indexer(indexer).run());
+
+    requests.send(KernelRequest::ListTasks(filter)).await.unwrap();
+    match responses.recv().await.unwrap() {
+        KernelResponse::Tasks(tasks) => tasks,
+        other => panic!("unexpected response {:?}", other),
+    }
+}
+
+fn summary(tasks: &[TaskComment]) -> Vec<(String, u32, String)> {
+    tasks
+        .iter()
+        .map(|t| (t.relative_path.to_string_lossy().into_owned(), t.line, t.tag.clone()))
+        .collect()
+}
+
+#[tokio::test]
+async fn lists_tasks_in_comments_only() {
+    let tasks = list_tasks(TaskFilter::default()).await;
+
+    assert_eq!(
+        summary(&tasks),
+        vec![
+            ("app.py".to_string(), 1, "HACK".to_string()),
+            ("main.rs".to_string(), 1, "TODO".to_string()),
+            ("main.rs".to_string(), 4, "FIXME".to_string()),
+        ]
+    );
+    assert_eq!(tasks[1].assignee.as_deref(), Some("ana"));
+    assert_eq!(tasks[1].text, "handle errors");
+}
+
+#[tokio::test]
+async fn filters_tasks_by_tag_and_assignee() {
+    let fixme = TaskFilter {
+        tags: vec!["fixme".to_string()],
+        ..Default::default()
+    };
+    assert_eq!(summary(&list_tasks(fixme).await), vec![("main.rs".to_string(), 4, "FIXME".to_string())]);
+
+    let ana = TaskFilter {
+        assignee: Some("Ana".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(summary(&list_tasks(ana).await), vec![("main.rs".to_string(), 1, "TODO".to_string())]);
+}
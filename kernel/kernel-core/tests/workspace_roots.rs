@@ -0,0 +1,89 @@
+use std::path::{Path, PathBuf};
+
+use kernel_core::{ProjectIndexer, SearchScope};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("meacode-roots-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write(path: &Path, content: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+/// `(root, relative path)` of every indexed file, sorted.
+async fn owners(indexer: &ProjectIndexer) -> Vec<(Option<String>, PathBuf)> {
+    let mut owners: Vec<_> = indexer
+        .files_in(&SearchScope::All)
+        .await
+        .into_iter()
+        .map(|f| (f.root, f.relative_path))
+        .collect();
+    owners.sort();
+    owners
+}
+
+fn owned(root: &str, relative: &str) -> (Option<String>, PathBuf) {
+    (Some(root.to_string()), PathBuf::from(relative))
+}
+
+#[tokio::test]
+async fn nested_root_files_move_between_roots() {
+    let dir = temp_dir("nested");
+    let outer = dir.join("outer");
+    write(&outer.join("main.rs"), "fn main() {}\n");
+    write(&outer.join("lib/util.rs"), "fn util() {}\n");
+
+    let indexer = ProjectIndexer::new(outer.clone());
+    indexer.index_all().await.unwrap();
+
+    // Adding the nested root takes over the files already indexed under it
+    let lib = indexer.add_root(outer.join("lib")).await;
+    assert_eq!(lib, "lib");
+    assert_eq!(owners(&indexer).await, vec![owned("lib", "util.rs"), owned("outer", "main.rs")]);
+
+    // The outer walk skips the nested root; its own walk indexes it
+    indexer.index_all().await.unwrap();
+    assert_eq!(owners(&indexer).await, vec![owned("lib", "util.rs"), owned("outer", "main.rs")]);
+
+    // Removing it hands the files back rather than dropping them
+    assert!(indexer.remove_root("lib").await);
+    assert_eq!(owners(&indexer).await, vec![owned("outer", "lib/util.rs"), owned("outer", "main.rs")]);
+    assert_eq!(indexer.find_symbols("util").await.len(), 1);
+    assert!(!indexer.remove_root("lib").await);
+}
+
+#[tokio::test]
+async fn removed_root_files_the_enclosing_root_ignores_are_dropped() {
+    let dir = temp_dir("ignored");
+    let outer = dir.join("outer");
+    write(&outer.join("main.rs"), "fn main() {}\n");
+    write(&outer.join("build/generated.rs"), "fn generated() {}\n");
+
+    let indexer = ProjectIndexer::new(outer.clone());
+    let build = indexer.add_root(outer.join("build")).await;
+    indexer.index_all().await.unwrap();
+    assert_eq!(owners(&indexer).await, vec![owned("build", "generated.rs"), owned("outer", "main.rs")]);
+
+    // `build` is in the default ignore rules of the outer root
+    assert!(indexer.remove_root(&build).await);
+    assert_eq!(owners(&indexer).await, vec![owned("outer", "main.rs")]);
+    assert!(indexer.find_symbols("generated").await.is_empty());
+}
+
+#[tokio::test]
+async fn removed_root_outside_every_other_root_drops_its_files() {
+    let dir = temp_dir("separate");
+    write(&dir.join("app/main.rs"), "fn main() {}\n");
+    write(&dir.join("shared/lib.rs"), "fn shared() {}\n");
+
+    let indexer = ProjectIndexer::new(dir.join("app"));
+    let shared = indexer.add_root(dir.join("shared")).await;
+    indexer.index_all().await.unwrap();
+
+    assert!(indexer.remove_root(&shared).await);
+    assert_eq!(owners(&indexer).await, vec![owned("app", "main.rs")]);
+}
@@ -1,5 +1,8 @@
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
+use kernel_core::tasks::{find_tasks, DEFAULT_TASK_TAGS};
+use kernel_core::LanguageRegistry;
 use tokio::sync::RwLock;
 use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::{
@@ -13,6 +16,7 @@ use tower_lsp::{Client, LanguageServer};
 pub struct Backend {
     client: Client,
     documents: Arc<RwLock<HashMap<Url, String>>>,
+    languages: Arc<RwLock<LanguageRegistry>>,
 }
 
 impl Backend {
@@ -20,11 +24,25 @@ impl Backend {
         Self {
             client,
             documents: Arc::new(RwLock::new(HashMap::new())),
+            languages: Arc::new(RwLock::new(LanguageRegistry::builtin())),
         }
     }
 
+    async fn document_language(&self, uri: &Url, text: &str) -> String {
+        let languages = self.languages.read().await;
+        languages.detect(Path::new(uri.path()), Some(text)).to_string()
+    }
+
     async fn analyze_document(&self, uri: &Url, text: &str) -> Vec<Diagnostic> {
         let mut diagnostics = Vec::new();
+        let language = self.document_language(uri, text).await;
+        let tasks = {
+            let languages = self.languages.read().await;
+            languages
+                .get(&language)
+                .map(|definition| find_tasks(text, definition, DEFAULT_TASK_TAGS))
+                .unwrap_or_default()
+        };
         let lines: Vec<&str> = text.lines().collect();
 
         for (line_idx, line) in lines.iter().enumerate() {
@@ -54,31 +72,8 @@ impl Backend {
                 });
             }
 
-            if line.contains("TODO") || line.contains("FIXME") {
-                diagnostics.push(Diagnostic {
-                    range: Range {
-                        start: Position {
-                            line: line_num,
-                            character: 0,
-                        },
-                        end: Position {
-                            line: line_num,
-                            character: line.len() as u32,
-                        },
-                    },
-                    severity: Some(DiagnosticSeverity::INFORMATION),
-                    code: Some(NumberOrString::String("todo".to_string())),
-                    code_description: None,
-                    source: Some("meacode-lsp".to_string()),
-                    message: format!("{} encontrado", if line.contains("TODO") { "TODO" } else { "FIXME" }),
-                    related_information: None,
-                    tags: None,
-                    data: None,
-                });
-            }
-
             // Check for potential errors in TypeScript/JavaScript
-            if line.contains("any ") && uri.path().ends_with(".ts") {
+            if line.contains("any ") && language == "typescript" {
                 diagnostics.push(Diagnostic {
                     range: Range {
                         start: Position {
@@ -102,6 +97,30 @@ impl Backend {
             }
         }
 
+        // Task tags are only reported inside real comments
+        for task in tasks {
+            diagnostics.push(Diagnostic {
+                range: Range {
+                    start: Position {
+                        line: task.line - 1,
+                        character: task.column,
+                    },
+                    end: Position {
+                        line: task.line - 1,
+                        character: task.column + task.tag.len() as u32,
+                    },
+                },
+                severity: Some(DiagnosticSeverity::INFORMATION),
+                code: Some(NumberOrString::String("todo".to_string())),
+                code_description: None,
+                source: Some("meacode-lsp".to_string()),
+                message: format!("{} encontrado", task.tag),
+                related_information: None,
+                tags: None,
+                data: None,
+            });
+        }
+
         diagnostics
     }
 
@@ -194,8 +213,18 @@ impl Backend {
 impl LanguageServer for Backend {
     async fn initialize(
         &self,
-        _: tower_lsp::lsp_types::InitializeParams,
+        params: tower_lsp::lsp_types::InitializeParams,
     ) -> Result<tower_lsp::lsp_types::InitializeResult> {
+        // The client may point us at a custom language registry file
+        if let Some(path) = params
+            .initialization_options
+            .as_ref()
+            .and_then(|o| o.get("languageRegistry"))
+            .and_then(|p| p.as_str())
+        {
+            *self.languages.write().await = LanguageRegistry::load_or_builtin(Path::new(path));
+        }
+
         let capabilities = ServerCapabilities {
             text_document_sync: Some(TextDocumentSyncCapability::Kind(
                 TextDocumentSyncKind::FULL,
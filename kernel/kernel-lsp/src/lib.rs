@@ -1,3 +1,5 @@
+use kernel_core::tasks::{find_tasks, DEFAULT_TASK_TAGS};
+use kernel_core::LanguageDefinition;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -57,7 +59,9 @@ pub struct EngineDiagnostic {
   pub end_col: u32,
 }
 
-pub fn engine_diagnostics(text: &str) -> Vec<EngineDiagnostic> {
+/// Task tags are only reported inside real comments, so `language` is needed
+/// to find them; without it none are reported.
+pub fn engine_diagnostics(text: &str, language: Option<&LanguageDefinition>) -> Vec<EngineDiagnostic> {
   let mut diagnostics = Vec::new();
 
   for (idx, line) in text.lines().enumerate() {
@@ -74,17 +78,6 @@ pub fn engine_diagnostics(text: &str) -> Vec<EngineDiagnostic> {
       });
     }
 
-    if let Some(col) = line.find("TODO") {
-      diagnostics.push(EngineDiagnostic {
-        message: "TODO pendiente (demo info)".to_string(),
-        severity: Some(3),
-        start_line: line_no,
-        start_col: (col as u32) + 1,
-        end_line: line_no,
-        end_col: (col as u32) + 1 + "TODO".len() as u32,
-      });
-    }
-
     if let Some(col) = line.find("any ") {
       diagnostics.push(EngineDiagnostic {
         message: "Tipo 'any' detectado (demo error)".to_string(),
@@ -97,5 +90,18 @@ pub fn engine_diagnostics(text: &str) -> Vec<EngineDiagnostic> {
     }
   }
 
+  if let Some(language) = language {
+    for task in find_tasks(text, language, DEFAULT_TASK_TAGS) {
+      diagnostics.push(EngineDiagnostic {
+        message: format!("{} pendiente", task.tag),
+        severity: Some(3),
+        start_line: task.line,
+        start_col: task.column + 1,
+        end_line: task.line,
+        end_col: task.column + 1 + task.tag.len() as u32,
+      });
+    }
+  }
+
   diagnostics
 }
@@ -0,0 +1,28 @@
+use kernel_core::LanguageRegistry;
+use kernel_lsp::engine_diagnostics;
+
+#[test]
+fn task_diagnostics_only_come_from_comments() {
+  let languages = LanguageRegistry::builtin();
+  let text = "const s = \"TODO: not a task\";\n// TODO: real task\n  /* FIXME(ana) later */\n";
+
+  let tasks: Vec<_> = engine_diagnostics(text, languages.get("typescript"))
+    .into_iter()
+    .filter(|d| d.severity == Some(3))
+    .map(|d| (d.message, d.start_line, d.start_col, d.end_col))
+    .collect();
+
+  assert_eq!(
+    tasks,
+    vec![
+      ("TODO pendiente".to_string(), 2, 4, 8),
+      ("FIXME pendiente".to_string(), 3, 6, 11),
+    ]
+  );
+}
+
+#[test]
+fn task_diagnostics_need_a_language() {
+  let diagnostics = engine_diagnostics("// TODO: real task\n", None);
+  assert!(diagnostics.is_empty());
+}
@@ -2,16 +2,26 @@
 
 use tokio::fs;
 use std::process::Stdio;
+use std::sync::{Arc, Mutex};
 use tokio::process::Command;
 use tauri::api::dialog::blocking::{FileDialogBuilder, MessageDialogBuilder};
 use tauri::api::dialog::{MessageDialogButtons, MessageDialogKind};
 use kernel_lsp::{engine_completions, engine_diagnostics, engine_hover};
+use kernel_core::{
+    ChunkHit, Feature, FeatureNotLicensed, KernelCore, KernelRequest, KernelResponse, LanguageRegistry,
+    LicenseManager, LicenseStatus, Quota, QuotaExceeded, ReplaceOutcome, ReplacePreview, ReplaceRequest,
+    TaskComment, TaskFilter,
+};
 use serde::Serialize;
+use tauri::Manager;
+use tokio::sync::{mpsc, oneshot};
 use tokio::time::{timeout, Duration};
+use kernel_ai::{ChatCompletion, ChatMessage, MessageRole, OllamaModel, OllamaProvider, ProviderConfig, StreamEvent};
 
 const MAX_READ_BYTES: u64 = 5 * 1024 * 1024; // 5MB
 const MAX_SAVE_BYTES: u64 = 10 * 1024 * 1024; // 10MB
 const COMMAND_TIMEOUT_SECS: u64 = 30; // evita locks por comandos colgados
+const AI_STREAM_EVENT: &str = "ai-stream";
 
 #[derive(Serialize)]
 struct AppInfo {
@@ -66,13 +76,127 @@ async fn get_app_info() -> AppInfo {
     }
 }
 
+type KernelEnvelope = (KernelRequest, oneshot::Sender<KernelResponse>);
+
+/// Canal con la tarea de kernel-core lanzada en `setup`. Cada petición lleva su propio
+/// `oneshot`, así una respuesta tardía de una petición abandonada no la recibe la siguiente.
+struct KernelChannel {
+    requests: mpsc::Sender<KernelEnvelope>,
+}
+
+impl KernelChannel {
+    /// Lanza la tarea que empareja las respuestas de kernel-core con sus peticiones;
+    /// kernel-core responde en orden, una respuesta por petición.
+    fn new(request_tx: mpsc::Sender<KernelRequest>, mut response_rx: mpsc::Receiver<KernelResponse>) -> Self {
+        let (requests, mut envelopes) = mpsc::channel::<KernelEnvelope>(32);
+        tauri::async_runtime::spawn(async move {
+            while let Some((request, reply)) = envelopes.recv().await {
+                if request_tx.send(request).await.is_err() {
+                    break;
+                }
+                match response_rx.recv().await {
+                    // Quien abandonó la espera ya soltó el receptor
+                    Some(response) => {
+                        let _ = reply.send(response);
+                    }
+                    None => break,
+                }
+            }
+        });
+
+        Self { requests }
+    }
+
+    async fn request(&self, request: KernelRequest) -> Result<KernelResponse, String> {
+        let (reply, response) = oneshot::channel();
+        self.requests
+            .send((request, reply))
+            .await
+            .map_err(|_| "kernel-core stopped".to_string())?;
+        timeout(Duration::from_secs(COMMAND_TIMEOUT_SECS), response)
+            .await
+            .map_err(|_| "kernel-core did not answer".to_string())?
+            .map_err(|_| "kernel-core stopped".to_string())
+    }
+}
+
 #[tauri::command]
-async fn ping_kernel() -> Result<KernelPingResult, String> {
-    // El kernel-core actualmente solo implementa un Ping demo.
-    // Para evitar spawns efímeros (y posibles colas/latencias), hacemos un healthcheck estable.
-    Ok(KernelPingResult {
-        status: "ok".to_string(),
-    })
+async fn ping_kernel(kernel: tauri::State<'_, KernelChannel>) -> Result<KernelPingResult, String> {
+    match kernel.request(KernelRequest::Ping).await? {
+        KernelResponse::Pong => Ok(KernelPingResult {
+            status: "ok".to_string(),
+        }),
+        other => Err(format!("Unexpected kernel response: {:?}", other)),
+    }
+}
+
+/// Envía una petición a kernel-core, que comprueba la licencia con el gestor compartido.
+async fn kernel_request(kernel: &KernelChannel, request: KernelRequest) -> Result<KernelResponse, CommandError> {
+    match kernel.request(request).await? {
+        KernelResponse::FeatureNotLicensed(e) => Err(e.into()),
+        KernelResponse::Error(message) => Err(message.into()),
+        response => Ok(response),
+    }
+}
+
+fn unexpected(response: KernelResponse) -> CommandError {
+    CommandError::Failed(format!("Unexpected kernel response: {:?}", response))
+}
+
+/// Abre `path` como workspace de kernel-core; se indexa en segundo plano.
+#[tauri::command]
+async fn open_workspace(path: String, kernel: tauri::State<'_, KernelChannel>) -> Result<(), CommandError> {
+    match kernel_request(&kernel, KernelRequest::OpenWorkspace { path: path.into() }).await? {
+        KernelResponse::WorkspaceOpened => Ok(()),
+        other => Err(unexpected(other)),
+    }
+}
+
+#[tauri::command]
+async fn preview_replace(
+    request: ReplaceRequest,
+    kernel: tauri::State<'_, KernelChannel>,
+) -> Result<ReplacePreview, CommandError> {
+    match kernel_request(&kernel, KernelRequest::PreviewReplace(request)).await? {
+        KernelResponse::ReplacePreview(preview) => Ok(preview),
+        other => Err(unexpected(other)),
+    }
+}
+
+#[tauri::command]
+async fn apply_replace(
+    preview: ReplacePreview,
+    kernel: tauri::State<'_, KernelChannel>,
+) -> Result<ReplaceOutcome, CommandError> {
+    match kernel_request(&kernel, KernelRequest::ApplyReplace(preview)).await? {
+        KernelResponse::Replaced(outcome) => Ok(outcome),
+        other => Err(unexpected(other)),
+    }
+}
+
+/// Comentarios TODO/FIXME/… del workspace abierto, ordenados por ruta y línea.
+#[tauri::command]
+async fn list_tasks(
+    filter: Option<TaskFilter>,
+    kernel: tauri::State<'_, KernelChannel>,
+) -> Result<Vec<TaskComment>, CommandError> {
+    match kernel_request(&kernel, KernelRequest::ListTasks(filter.unwrap_or_default())).await? {
+        KernelResponse::Tasks(tasks) => Ok(tasks),
+        other => Err(unexpected(other)),
+    }
+}
+
+/// Fragmentos de código del workspace relevantes para `query`, como contexto del chat.
+#[tauri::command]
+async fn retrieve_chunks(
+    query: String,
+    k: usize,
+    kernel: tauri::State<'_, KernelChannel>,
+) -> Result<Vec<ChunkHit>, CommandError> {
+    match kernel_request(&kernel, KernelRequest::RetrieveChunks { query, k }).await? {
+        KernelResponse::Chunks(chunks) => Ok(chunks),
+        other => Err(unexpected(other)),
+    }
 }
 
 #[tauri::command]
@@ -174,6 +298,268 @@ async fn read_file(path: String) -> Result<FileContent, String> {
     Ok(FileContent { path, content })
 }
 
+#[tauri::command]
+async fn detect_language(
+    path: String,
+    languages: tauri::State<'_, LanguageRegistry>,
+) -> Result<String, String> {
+    let path_obj = std::path::Path::new(&path);
+    if let Some(language) = languages.detect_by_path(path_obj) {
+        return Ok(language.to_string());
+    }
+
+    // Files without a known name or extension may still carry a shebang
+    let meta = fs::metadata(&path).await.map_err(|e| e.to_string())?;
+    if meta.is_file() && meta.len() <= MAX_READ_BYTES {
+        if let Ok(content) = fs::read_to_string(&path).await {
+            return Ok(languages.detect(path_obj, Some(&content)).to_string());
+        }
+    }
+
+    Ok(languages.detect(path_obj, None).to_string())
+}
+
+/// Error for gated commands: a typed object when the license lacks the
+/// feature or a quota is used up, a plain message otherwise, like the
+/// other commands.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum CommandError {
+    FeatureNotLicensed(FeatureNotLicensed),
+    QuotaExceeded(QuotaExceeded),
+    Failed(String),
+}
+
+impl From<FeatureNotLicensed> for CommandError {
+    fn from(e: FeatureNotLicensed) -> Self {
+        CommandError::FeatureNotLicensed(e)
+    }
+}
+
+impl From<QuotaExceeded> for CommandError {
+    fn from(e: QuotaExceeded) -> Self {
+        CommandError::QuotaExceeded(e)
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Failed(message)
+    }
+}
+
+/// Checked at the start of every command that needs a licensed feature.
+fn ensure_feature(manager: &Mutex<LicenseManager>, feature: Feature) -> Result<(), CommandError> {
+    let manager = manager.lock().map_err(|e| e.to_string())?;
+    manager.require(feature)?;
+    Ok(())
+}
+
+/// Lets the frontend hide or disable premium UI. Only advisory: every
+/// premium command checks the license itself.
+#[tauri::command]
+async fn require_feature(
+    feature: Feature,
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<(), CommandError> {
+    ensure_feature(&manager, feature)
+}
+
+/// Counts one use of a quota, e.g. an AI request made from the frontend.
+#[tauri::command]
+async fn consume_quota(
+    quota: Quota,
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<(), CommandError> {
+    let mut manager = manager.lock().map_err(|e| e.to_string())?;
+    manager.consume(quota)?;
+    Ok(())
+}
+
+#[derive(Clone, Serialize)]
+struct AIStreamPayload {
+    stream_id: String,
+    event: StreamEvent,
+}
+
+/// Streams a chat reply to the window as `ai-stream` events tagged with the
+/// caller's `stream_id`, and resolves once the `done` or `error` event is out.
+#[tauri::command]
+async fn stream_ai_chat(
+    stream_id: String,
+    config: ProviderConfig,
+    messages: Vec<ChatMessage>,
+    window: tauri::Window,
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<(), CommandError> {
+    ensure_feature(&manager, Feature::AIChat)?;
+    manager
+        .lock()
+        .map_err(|e| e.to_string())?
+        .consume(Quota::AiRequestsPerDay)?;
+
+    let provider = config.build().map_err(|e| e.to_string())?;
+    let (sender, mut receiver) = mpsc::channel(64);
+    let forward = async {
+        while let Some(event) = receiver.recv().await {
+            let payload = AIStreamPayload {
+                stream_id: stream_id.clone(),
+                event,
+            };
+            if let Err(e) = window.emit(AI_STREAM_EVENT, payload) {
+                eprintln!("Error emitting AI stream event: {}", e);
+            }
+        }
+    };
+    tokio::join!(provider.stream_chat(messages, sender), forward);
+
+    Ok(())
+}
+
+/// One chat reply, for callers that do not need streaming.
+#[tauri::command]
+async fn ai_chat(
+    config: ProviderConfig,
+    messages: Vec<ChatMessage>,
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<ChatCompletion, CommandError> {
+    ensure_feature(&manager, Feature::AIChat)?;
+    manager
+        .lock()
+        .map_err(|e| e.to_string())?
+        .consume(Quota::AiRequestsPerDay)?;
+
+    let completion = config
+        .build()
+        .map_err(|e| e.to_string())?
+        .complete(messages)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(completion)
+}
+
+/// Pide al modelo una versión refactorizada de `code`; devuelve solo el código.
+#[tauri::command]
+async fn ai_refactor(
+    config: ProviderConfig,
+    code: String,
+    language: Option<String>,
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<String, CommandError> {
+    ensure_feature(&manager, Feature::AdvancedRefactoring)?;
+    ensure_feature(&manager, Feature::AIChat)?;
+    manager
+        .lock()
+        .map_err(|e| e.to_string())?
+        .consume(Quota::AiRequestsPerDay)?;
+
+    let language = language.unwrap_or_default();
+    let in_language = if language.is_empty() {
+        String::new()
+    } else {
+        format!(" en {}", language)
+    };
+    let messages = vec![
+        ChatMessage {
+            role: MessageRole::System,
+            content: "Eres un experto en programación. Refactoriza código para mejorarlo manteniendo la funcionalidad. Devuelve solo el código refactorizado, sin explicaciones adicionales.".to_string(),
+        },
+        ChatMessage {
+            role: MessageRole::User,
+            content: format!(
+                "Refactoriza este código{} para mejorarlo:\n\n```{}\n{}\n```\n\nDevuelve solo el código refactorizado.",
+                in_language, language, code
+            ),
+        },
+    ];
+
+    let completion = config
+        .build()
+        .map_err(|e| e.to_string())?
+        .complete(messages)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(completion.content)
+}
+
+#[tauri::command]
+async fn list_ollama_models(base_url: Option<String>) -> Result<Vec<OllamaModel>, String> {
+    let mut provider = OllamaProvider::new();
+    if let Some(base_url) = base_url {
+        provider = provider.with_base_url(base_url);
+    }
+    provider.list_models().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn activate_license(
+    license: String,
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<LicenseStatus, String> {
+    let mut manager = manager.lock().map_err(|e| e.to_string())?;
+    manager.activate_license(&license).map_err(|e| e.to_string())?;
+    Ok(manager.status())
+}
+
+/// `seat_token` is the signed seat token issued for this machine's fingerprint;
+/// `release`, the seat release of the machine that held the seat before, if any.
+#[tauri::command]
+async fn activate_seat(
+    license: String,
+    seat_token: String,
+    release: Option<String>,
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<LicenseStatus, String> {
+    let mut manager = manager.lock().map_err(|e| e.to_string())?;
+    manager
+        .activate_seat(&license, &seat_token, release.as_deref())
+        .map_err(|e| e.to_string())?;
+    Ok(manager.status())
+}
+
+/// Returns the release notice to send when asking for a seat token for another machine.
+#[tauri::command]
+async fn release_seat(
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<String, String> {
+    let mut manager = manager.lock().map_err(|e| e.to_string())?;
+    manager.release_seat().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_machine_fingerprint(
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<String, String> {
+    let manager = manager.lock().map_err(|e| e.to_string())?;
+    manager.fingerprint().map(str::to_string).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn get_license_status(
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<LicenseStatus, String> {
+    let manager = manager.lock().map_err(|e| e.to_string())?;
+    Ok(manager.status())
+}
+
+#[tauri::command]
+async fn start_trial(
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<LicenseStatus, String> {
+    let mut manager = manager.lock().map_err(|e| e.to_string())?;
+    manager.start_trial().map_err(|e| e.to_string())?;
+    Ok(manager.status())
+}
+
+#[tauri::command]
+async fn deactivate_license(
+    manager: tauri::State<'_, Arc<Mutex<LicenseManager>>>,
+) -> Result<LicenseStatus, String> {
+    let mut manager = manager.lock().map_err(|e| e.to_string())?;
+    manager.deactivate().map_err(|e| e.to_string())?;
+    Ok(manager.status())
+}
+
 #[tauri::command]
 async fn open_folder() -> Result<Option<String>, String> {
     let path = FileDialogBuilder::new()
@@ -392,14 +778,18 @@ async fn lsp_hover(symbol: String, language: Option<String>) -> Result<LspHoverR
 }
 
 #[tauri::command]
-async fn lsp_diagnostics(text: String, language: Option<String>) -> Result<Vec<LspDiagnostic>, String> {
+async fn lsp_diagnostics(
+    text: String,
+    language: Option<String>,
+    languages: tauri::State<'_, LanguageRegistry>,
+) -> Result<Vec<LspDiagnostic>, String> {
     // Proxy LSP por lenguaje (ver comentario en `lsp_completion`).
-    match language.as_deref() {
-        Some("typescript") | Some("javascript") => {}
+    let language = match language.as_deref() {
+        Some(id @ ("typescript" | "javascript")) => languages.get(id),
         _ => return Ok(vec![]),
-    }
+    };
 
-    let engine_diags = engine_diagnostics(&text);
+    let engine_diags = engine_diagnostics(&text, language);
 
     let diagnostics = engine_diags
         .into_iter()
@@ -418,11 +808,77 @@ async fn lsp_diagnostics(text: String, language: Option<String>) -> Result<Vec<L
 
 fn main() {
     tauri::Builder::default()
+        .setup(|app| {
+            // User overrides live in <config dir>/languages.json
+            let languages = app
+                .path_resolver()
+                .app_config_dir()
+                .map(|dir| LanguageRegistry::load_or_builtin(&dir.join("languages.json")))
+                .unwrap_or_default();
+            app.manage(languages.clone());
+
+            // Activated licenses, the trial record and quota usage live in the config dir.
+            // kernel-core shares the same manager, so activations reach its gated requests.
+            // Revocation lists ship as a resource with updates or are dropped in the config dir.
+            let mut license = LicenseManager::new();
+            if let Some(path) = app.path_resolver().resolve_resource("revocations.json") {
+                license = license.with_revocation_list(path);
+            }
+            // A marker in the local data dir keeps the trial used if trial.json is deleted.
+            if let Some(dir) = app.path_resolver().app_local_data_dir() {
+                license = license.with_trial_marker(dir.join(".trial-marker"));
+            }
+            if let Some(dir) = app.path_resolver().app_config_dir() {
+                license = license
+                    .with_storage(dir.join("license.json"))
+                    .with_trial_storage(dir.join("trial.json"))
+                    .with_usage_storage(dir.join("usage.json"))
+                    .with_revocation_list(dir.join("revocations.json"));
+                if let Err(e) = license.load() {
+                    eprintln!("Error loading saved license: {}", e);
+                }
+            }
+            let license = Arc::new(Mutex::new(license));
+
+            // The workspace is indexed when the frontend opens it with `open_workspace`
+            let (request_tx, request_rx) = mpsc::channel(32);
+            let (response_tx, response_rx) = mpsc::channel(32);
+            let kernel = KernelCore::new(request_rx, response_tx)
+                .with_license(license.clone())
+                .with_languages(languages);
+            tauri::async_runtime::spawn(async move {
+                if let Err(e) = kernel.run().await {
+                    eprintln!("[kernel-core] error: {:?}", e);
+                }
+            });
+            app.manage(KernelChannel::new(request_tx, response_rx));
+            app.manage(license);
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             get_app_info,
             ping_kernel,
+            open_workspace,
+            preview_replace,
+            apply_replace,
+            retrieve_chunks,
+            list_tasks,
             list_dir,
             read_file,
+            detect_language,
+            activate_license,
+            activate_seat,
+            release_seat,
+            get_machine_fingerprint,
+            get_license_status,
+            start_trial,
+            deactivate_license,
+            require_feature,
+            consume_quota,
+            stream_ai_chat,
+            ai_chat,
+            ai_refactor,
+            list_ollama_models,
             open_folder,
             open_file,
             save_file,
@@ -1,6 +1,6 @@
 import { invoke } from '@tauri-apps/api/tauri'
+import { listen } from '@tauri-apps/api/event'
 import {
-  getChatCompletionsUrl,
   getModelsListUrl,
   loadAISettings,
   type AISettings,
@@ -36,6 +36,96 @@ export async function pingKernel(): Promise<KernelPingResult> {
   }
 }
 
+/** Abre la carpeta como workspace de kernel-core; se indexa en segundo plano. */
+export async function openWorkspace(path: string): Promise<void> {
+  await callKernel<void>('open_workspace', { path })
+}
+
+export type ReplaceRequest = {
+  query: string
+  replacement: string
+  is_regex?: boolean
+  case_sensitive?: boolean
+}
+
+export type ReplaceEdit = {
+  line: number
+  column: number
+  start: number
+  end: number
+  original: string
+  replacement: string
+  line_text: string
+  /** Solo se aplican las ediciones seleccionadas */
+  selected: boolean
+}
+
+export type ReplacePreview = {
+  files: {
+    path: string
+    relative_path: string
+    content_hash: string
+    edits: ReplaceEdit[]
+  }[]
+}
+
+export type ReplaceOutcome = {
+  files_changed: number
+  edits_applied: number
+}
+
+/** Requiere `advanced_refactoring`; rechaza con `FeatureNotLicensed` si falta. */
+export async function previewReplace(request: ReplaceRequest): Promise<ReplacePreview> {
+  return callKernel<ReplacePreview>('preview_replace', { request })
+}
+
+export async function applyReplace(preview: ReplacePreview): Promise<ReplaceOutcome> {
+  return callKernel<ReplaceOutcome>('apply_replace', { preview })
+}
+
+export type TaskComment = {
+  path: string
+  relative_path: string
+  root: string | null
+  line: number
+  column: number
+  tag: string
+  assignee: string | null
+  text: string
+}
+
+/** Campos vacíos no filtran; `path` y `text` buscan subcadenas sin distinguir mayúsculas. */
+export type TaskFilter = {
+  tags?: string[]
+  assignee?: string
+  path?: string
+  text?: string
+}
+
+/** Lista de tareas del workspace abierto con `openWorkspace`. */
+export async function listTasks(filter?: TaskFilter): Promise<TaskComment[]> {
+  return callKernel<TaskComment[]>('list_tasks', { filter })
+}
+
+export type ChunkHit = {
+  chunk: {
+    path: string
+    relative_path: string
+    language: string
+    start_line: number
+    end_line: number
+    symbol: string | null
+    kind: string | null
+    content: string
+  }
+  score: number
+}
+
+/** Fragmentos del workspace para dar contexto al chat; requiere `ai_chat`. */
+export async function retrieveChunks(query: string, k: number): Promise<ChunkHit[]> {
+  return callKernel<ChunkHit[]>('retrieve_chunks', { query, k })
+}
+
 export type FileEntry = {
   name: string
   path: string
@@ -66,6 +156,132 @@ export async function readFile(path: string): Promise<FileContent | null> {
   }
 }
 
+export async function detectFileLanguage(path: string): Promise<string | null> {
+  try {
+    return await callKernel<string>('detect_language', { path })
+  } catch (err) {
+    console.error('Error calling detect_language', err)
+    return null
+  }
+}
+
+export type Feature =
+  | 'basic_editor'
+  | 'file_explorer'
+  | 'terminal'
+  | 'basic_lsp'
+  | 'ai_completion'
+  | 'ai_chat'
+  | 'advanced_refactoring'
+  | 'gpu_acceleration'
+  | 'custom_themes'
+  | 'plugin_system'
+  | 'cloud_sync'
+
+export type Quota = 'ai_requests_per_day' | 'max_plugins'
+
+export type QuotaUsage = {
+  quota: Quota
+  limit: number
+  used: number | null
+  resets_at: number | null
+}
+
+export type LicenseInfo = {
+  is_premium: boolean
+  features: Feature[]
+  quotas: Partial<Record<Quota, number>>
+  expires_at: number | null
+  license_id: string | null
+  licensee: string | null
+  tier: 'free' | 'trial' | 'premium'
+  downgraded_from: 'free' | 'trial' | 'premium' | null
+  seats: number | null
+  seat: number | null
+  revoked: boolean
+}
+
+export type LicenseStatus = {
+  license: LicenseInfo
+  state: 'perpetual' | 'active' | 'grace' | 'expired'
+  is_premium: boolean
+  remaining_days: number | null
+  grace_days_remaining: number | null
+  quotas: QuotaUsage[]
+}
+
+export async function activateLicense(license: string): Promise<LicenseStatus> {
+  return callKernel<LicenseStatus>('activate_license', { license })
+}
+
+/**
+ * `seatToken`: token firmado que se emite para la huella de este equipo (`getMachineFingerprint`).
+ * `release`: aviso de liberación del equipo que tenía el puesto antes, si cambia de manos.
+ */
+export async function activateSeat(license: string, seatToken: string, release?: string): Promise<LicenseStatus> {
+  return callKernel<LicenseStatus>('activate_seat', { license, seatToken, release: release ?? null })
+}
+
+/** Libera el puesto de este equipo; devuelve el aviso firmado a enviar para pedir un token para otro. */
+export async function releaseSeat(): Promise<string> {
+  return callKernel<string>('release_seat')
+}
+
+export async function getMachineFingerprint(): Promise<string> {
+  return callKernel<string>('get_machine_fingerprint')
+}
+
+export async function getLicenseStatus(): Promise<LicenseStatus | null> {
+  try {
+    return await callKernel<LicenseStatus>('get_license_status')
+  } catch (err) {
+    console.error('Error calling get_license_status', err)
+    return null
+  }
+}
+
+export async function startTrial(): Promise<LicenseStatus> {
+  return callKernel<LicenseStatus>('start_trial')
+}
+
+export async function deactivateLicense(): Promise<LicenseStatus> {
+  return callKernel<LicenseStatus>('deactivate_license')
+}
+
+export type FeatureNotLicensed = {
+  kind: 'feature_not_licensed'
+  feature: Feature
+  tier: LicenseInfo['tier']
+}
+
+export function isFeatureNotLicensed(err: unknown): err is FeatureNotLicensed {
+  return typeof err === 'object' && err !== null && (err as FeatureNotLicensed).kind === 'feature_not_licensed'
+}
+
+export type QuotaExceeded = {
+  kind: 'quota_exceeded'
+  quota: Quota
+  limit: number
+  resets_at: number | null
+}
+
+/**
+ * Rechaza con `FeatureNotLicensed` si la licencia no incluye `feature`. Solo sirve para
+ * adaptar la UI: los comandos premium comprueban la licencia por sí mismos.
+ */
+export async function requireFeature(feature: Feature): Promise<void> {
+  await callKernel<void>('require_feature', { feature })
+}
+
+export function isQuotaExceeded(err: unknown): err is QuotaExceeded {
+  return typeof err === 'object' && err !== null && (err as QuotaExceeded).kind === 'quota_exceeded'
+}
+
+/** Cuenta un uso de `quota`; rechaza con `QuotaExceeded` al llegar al límite. */
+export async function consumeQuota(quota: Quota): Promise<void> {
+  await callKernel<void>('consume_quota', { quota })
+}
+
 export type LspDiagnostic = {
   message: string
   severity?: number
@@ -267,6 +483,111 @@ export type ChatCompletionResult = {
   model: string
 }
 
+export type AIProviderConfig = {
+  provider: 'openai' | 'openai_compatible' | 'anthropic' | 'ollama' | 'local'
+  /** Nombre con el que se registra un proveedor `openai_compatible` */
+  name?: string
+  api_key?: string
+  base_url?: string
+  model?: string
+  temperature?: number
+  max_tokens?: number
+  /** Cabeceras extra en cada petición (`openai_compatible`) */
+  headers?: Record<string, string>
+  timeout_secs?: number
+  /** Segundos que Ollama mantiene el modelo cargado (`ollama`); negativo = siempre */
+  keep_alive_secs?: number
+}
+
+export type AIStreamEvent =
+  | { type: 'delta'; text: string }
+  | {
+      type: 'done'
+      stop_reason: string | { other: string } | null
+      usage: { input_tokens: number; output_tokens: number } | null
+    }
+  | { type: 'error'; message: string; status: number | null; kind: string | null }
+
+/**
+ * Ollama usa su API nativa; el resto de modos hablan la API de OpenAI.
+ * En modo `openai_compatible` la API key es opcional (LM Studio, vLLM locales).
+ */
+export function providerConfigFromSettings(
+  settings: AISettings,
+  modelId: string,
+  temperature?: number,
+): AIProviderConfig {
+  if (settings.mode === 'ollama') {
+    return {
+      provider: 'ollama',
+      base_url: ollamaServerUrl(settings.baseUrl),
+      model: modelId,
+      temperature,
+      keep_alive_secs: settings.keepAliveSecs ?? undefined,
+    }
+  }
+  if (settings.mode === 'openai_compatible') {
+    const apiKey = settings.apiKey.trim()
+    return {
+      provider: 'openai_compatible',
+      name: settings.providerName.trim() || undefined,
+      api_key: apiKey || undefined,
+      base_url: settings.baseUrl,
+      model: modelId,
+      temperature,
+    }
+  }
+  return {
+    provider: 'openai',
+    api_key: settings.apiKey.trim(),
+    base_url: settings.baseUrl,
+    model: modelId,
+    temperature,
+  }
+}
+
+/** Los ajustes guardan la URL OpenAI-compatible (`.../v1`); la API nativa cuelga de la raíz. */
+function ollamaServerUrl(baseUrl: string): string {
+  return baseUrl.trim().replace(/\/+$/, '').replace(/\/v1$/, '')
+}
+
+export type OllamaModel = {
+  name: string
+  size: number
+  modified_at: string | null
+  details: {
+    family: string | null
+    parameter_size: string | null
+    quantization_level: string | null
+  } | null
+}
+
+export async function listOllamaModels(baseUrl?: string): Promise<OllamaModel[]> {
+  return callKernel<OllamaModel[]>('list_ollama_models', {
+    baseUrl: baseUrl ? ollamaServerUrl(baseUrl) : null,
+  })
+}
+
+/**
+ * Chat en streaming desde el kernel: `onEvent` recibe los `delta` según llegan y
+ * termina con un único `done` o `error`. Resuelve cuando el stream ha acabado.
+ */
+export async function streamAIChat(
+  config: AIProviderConfig,
+  messages: NexusifyMessage[],
+  onEvent: (event: AIStreamEvent) => void,
+): Promise<void> {
+  const streamId = `${Date.now()}-${Math.random().toString(36).slice(2)}`
+  const unlisten = await listen<{ stream_id: string; event: AIStreamEvent }>('ai-stream', (e) => {
+    if (e.payload.stream_id === streamId) onEvent(e.payload.event)
+  })
+  try {
+    await callKernel<void>('stream_ai_chat', { streamId, config, messages })
+  } finally {
+    unlisten()
+  }
+}
+
 const LIST_MODELS_TIMEOUT_MS = 20_000
 
 /**
@@ -386,10 +707,9 @@ export function parseAIError(error: unknown): AIError {
   }
 }
 
-const CHAT_TIMEOUT_MS = 120_000
-
 /**
- * Chat completions con API estilo OpenAI (Nexusify, OpenAI, LM Studio, Ollama /v1, etc.).
+ * Chat completions con el proveedor configurado (Nexusify, OpenAI, LM Studio, Ollama, etc.).
+ * La petición la hace el backend, que comprueba la licencia y la cuota.
  * `modelId` obligatorio (usar `resolveModelForTask` si hace falta).
  */
 export async function chatCompletion(
@@ -398,7 +718,7 @@ export async function chatCompletion(
   temperature: number = 0.7,
   modelId?: string,
 ): Promise<ChatCompletionResult> {
-  if (settings.mode !== 'ollama' && !settings.apiKey?.trim()) {
+  if (settings.mode === 'nexusify' && !settings.apiKey?.trim()) {
     throw new Error('API key no configurada')
   }
 
@@ -407,50 +727,11 @@ export async function chatCompletion(
     throw new Error('Modelo no configurado')
   }
 
-  const url = getChatCompletionsUrl(settings)
-  const controller = new AbortController()
-  const timer = setTimeout(() => controller.abort(), CHAT_TIMEOUT_MS)
-
-  const headers: Record<string, string> = {
-    'Content-Type': 'application/json',
-  }
-  if (settings.apiKey?.trim()) {
-    headers['Authorization'] = `Bearer ${settings.apiKey.trim()}`
-  }
-
-  try {
-    const response = await fetch(url, {
-      method: 'POST',
-      headers,
-      body: JSON.stringify({
-        model,
-        messages,
-        temperature,
-      }),
-      signal: controller.signal,
-    })
-
-    if (!response.ok) {
-      const errorData = await response.json().catch(() => ({ error: { message: `HTTP ${response.status}` } }))
-      const errorMessage =
-        (errorData as { error?: { message?: string } }).error?.message || `HTTP ${response.status}`
-      throw new Error(errorMessage)
-    }
-
-    const data: NexusifyChatResponse = await response.json()
-    const content = data.choices[0]?.message?.content || 'Sin respuesta'
-    const reported = (data.model && data.model.trim()) || model
-    return { content, model: reported }
-  } catch (err) {
-    const maybeErr = err as { name?: string }
-    if (maybeErr?.name === 'AbortError') {
-      throw new Error(`Solicitud agotó el tiempo de espera (${CHAT_TIMEOUT_MS}ms)`)
-    }
-    console.error('Error chat completion:', err)
-    throw err
-  } finally {
-    clearTimeout(timer)
-  }
+  const completion = await callKernel<{ content: string }>('ai_chat', {
+    config: providerConfigFromSettings(settings, model, temperature),
+    messages,
+  })
+  return { content: completion.content || 'Sin respuesta', model }
 }
 
 /** Compatibilidad: Nexusify con clave explícita. */
@@ -530,17 +811,9 @@ export async function refactorCodeWithAI(
 ): Promise<string> {
   const s = settings ?? loadAISettings()
   const modelId = await resolveModelForTask(s, 'code', code.slice(0, 4000))
-  const messages: NexusifyMessage[] = [
-    {
-      role: 'system',
-      content: 'Eres un experto en programación. Refactoriza código para mejorarlo manteniendo la funcionalidad. Devuelve solo el código refactorizado, sin explicaciones adicionales.',
-    },
-    {
-      role: 'user',
-      content: `Refactoriza este código${language ? ` en ${language}` : ''} para mejorarlo:\n\n\`\`\`${language || ''}\n${code}\n\`\`\`\n\nDevuelve solo el código refactorizado.`,
-    },
-  ]
-
-  const r = await chatCompletion(s, messages, 0.7, modelId)
-  return r.content
+  return callKernel<string>('ai_refactor', {
+    config: providerConfigFromSettings(s, modelId, 0.7),
+    code,
+    language: language ?? null,
+  })
 }
\ No newline at end of file
@@ -1,5 +1,12 @@
 import React, { useState, useRef, useEffect } from 'react'
-import { chatCompletion, resolveModelForTask, type NexusifyMessage } from '../../api/bridge'
+import {
+  isFeatureNotLicensed,
+  isQuotaExceeded,
+  providerConfigFromSettings,
+  resolveModelForTask,
+  streamAIChat,
+  type NexusifyMessage,
+} from '../../api/bridge'
 import {
   loadAISettings,
   saveAISettings,
@@ -107,7 +114,22 @@ export const AIChatThemed: React.FC = () => {
 
     try {
       const modelId = await resolveModelForTask(settings, 'chat', userMsg.content)
-      const { content, model: usedModel } = await chatCompletion(settings, history, 0.7, modelId)
+      let content = ''
+      let streamError: string | null = null
+
+      // Muestra la respuesta según llega
+      await streamAIChat(providerConfigFromSettings(settings, modelId, 0.7), history, (event) => {
+        if (event.type === 'delta') {
+          content += event.text
+          const partial = content
+          setMessages((prev) => prev.map((m) => (m.loading ? { ...m, content: partial } : m)))
+        } else if (event.type === 'error') {
+          streamError = event.message
+        }
+      })
+      if (streamError) {
+        throw new Error(streamError)
+      }
 
       setMessages((prev) => {
         const next = [...prev]
@@ -118,7 +140,7 @@ export const AIChatThemed: React.FC = () => {
             role: 'assistant',
             content,
             loading: false,
-            usedModel,
+            usedModel: modelId,
           }
         }
         return next
@@ -131,7 +153,11 @@ export const AIChatThemed: React.FC = () => {
           next[loadingIndex] = {
             id: next[loadingIndex].id,
             role: 'assistant',
-            content: `Error: ${String(err)}`,
+            content: isFeatureNotLicensed(err)
+              ? 'El chat con IA requiere una licencia Premium o de prueba.'
+              : isQuotaExceeded(err)
+                ? `Has alcanzado el límite de ${err.limit} peticiones de IA por día.`
+                : `Error: ${String(err)}`,
             loading: false,
           }
         }
@@ -211,6 +237,19 @@ export const AIChatThemed: React.FC = () => {
               : 'Ej: https://api.openai.com/v1 o http://localhost:1234/v1 (LM Studio)'}
           </p>
 
+          {settings.mode === 'openai_compatible' && (
+            <>
+              <label className="block text-[10px] text-neutral-400">Nombre del proveedor</label>
+              <input
+                type="text"
+                value={settings.providerName}
+                onChange={(e) => setSettings((s) => ({ ...s, providerName: e.target.value }))}
+                placeholder="openai_compatible"
+                className="w-full text-[10px] bg-neutral-800 border border-neutral-700 rounded px-2 py-1 text-neutral-200 placeholder-neutral-600"
+              />
+            </>
+          )}
+
           <label className="block text-[10px] text-neutral-400">Modelo</label>
           <select
             value={settings.modelSelection}
@@ -230,6 +269,26 @@ export const AIChatThemed: React.FC = () => {
             por heurística (GPT-4, Claude, Gemini, Ollama grandes, etc.).
           </p>
 
+          {settings.mode === 'ollama' && (
+            <>
+              <label className="block text-[10px] text-neutral-400">Mantener modelo cargado (segundos)</label>
+              <input
+                type="number"
+                step={1}
+                value={settings.keepAliveSecs ?? ''}
+                onChange={(e) =>
+                  setSettings((s) => ({
+                    ...s,
+                    keepAliveSecs: Number.isFinite(e.target.valueAsNumber) ? Math.trunc(e.target.valueAsNumber) : null,
+                  }))
+                }
+                placeholder="Por defecto de Ollama (300)"
+                className="w-full text-[10px] bg-neutral-800 border border-neutral-700 rounded px-2 py-1 text-neutral-200 placeholder-neutral-600"
+              />
+              <p className="text-[9px] text-neutral-600">0 descarga el modelo al responder; -1 lo mantiene siempre.</p>
+            </>
+          )}
+
           {settings.mode !== 'ollama' && (
             <>
               <label className="block text-[10px] text-neutral-400">API key</label>
@@ -282,7 +341,7 @@ export const AIChatThemed: React.FC = () => {
             >
               {m.role === 'assistant' ? 'IA' : 'Tú'}:
             </span>
-            {m.loading ? (
+            {m.loading && !m.content ? (
               <span className="text-neutral-400 italic">Pensando...</span>
             ) : (
               <div className="mt-0.5">
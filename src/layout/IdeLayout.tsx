@@ -8,7 +8,7 @@ import { CommandPalette, type Command } from '../shared/components/CommandPalett
 import { SettingsPanel } from '../features/settings/SettingsPanel'
 import { RunDebugPanel } from '../features/terminal/RunDebugPanel'
 import { WelcomeScreen } from '../features/editor/WelcomeScreen'
-import { getAppInfo, pingKernel, readFile, listDir, openFolder as openFolderDialog, openFile as openFileDialog, saveFileAs } from '../api/bridge'
+import { getAppInfo, pingKernel, readFile, listDir, openFolder as openFolderDialog, openFile as openFileDialog, openWorkspace, saveFileAs } from '../api/bridge'
 import { useEditor } from '../shared/hooks/useEditor'
 import { showToast } from '../shared/utils/toast'
 import { loadFeatureFlags, type FeatureFlagsState } from '../shared/hooks/useFeatureFlags'
@@ -211,6 +211,7 @@ export const IdeLayout: React.FC = () => {
     const path = await openFolderDialog()
     if (path) {
       setWorkspacePath(path)
+      openWorkspace(path).catch((err) => console.error('Error calling open_workspace', err))
       try {
         localStorage.setItem('meacode-welcome-dismissed', 'true')
       } catch {
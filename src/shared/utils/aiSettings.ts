@@ -13,10 +13,14 @@ export type AISettings = {
   apiKey: string
   /** Sin barra final. Incluye /v1 si la API es OpenAI-compatible (ej. Ollama: http://127.0.0.1:11434/v1) */
   baseUrl: string
+  /** Nombre del proveedor OpenAI-compatible (ej. "LM Studio"); vacío usa `openai_compatible` */
+  providerName: string
   /** manual: usa `model`. auto_best: lista /v1/models y elige el más capaz. */
   modelSelection: ModelSelectionMode
   /** ID del modelo o respaldo si auto_best falla al listar */
   model: string
+  /** Ollama: segundos que el modelo sigue cargado tras responder; negativo = siempre, null = valor de Ollama */
+  keepAliveSecs: number | null
 }
 
 const STORAGE_KEY = 'meacode-ai-settings'
@@ -41,11 +45,17 @@ function defaultSettings(): AISettings {
     mode: 'nexusify',
     apiKey: '',
     baseUrl: defaultBaseUrl('nexusify'),
+    providerName: '',
     modelSelection: 'manual',
     model: 'gpt-4',
+    keepAliveSecs: null,
   }
 }
 
+function normalizeKeepAlive(value: unknown): number | null {
+  return typeof value === 'number' && Number.isFinite(value) ? Math.trunc(value) : null
+}
+
 /** URL completa POST .../chat/completions (OpenAI-compatible, incluye Ollama). */
 export function getChatCompletionsUrl(settings: AISettings): string {
   const base = settings.baseUrl.trim().replace(/\/+$/, '')
@@ -74,8 +84,10 @@ export function loadAISettings(): AISettings {
         mode,
         apiKey: typeof parsed.apiKey === 'string' ? parsed.apiKey : '',
         baseUrl,
+        providerName: typeof parsed.providerName === 'string' ? parsed.providerName.trim() : '',
         modelSelection: sel,
         model: typeof parsed.model === 'string' && parsed.model.trim() ? parsed.model.trim() : 'gpt-4',
+        keepAliveSecs: normalizeKeepAlive(parsed.keepAliveSecs),
       }
     }
   } catch {
@@ -89,8 +101,10 @@ export function loadAISettings(): AISettings {
         mode: 'nexusify',
         apiKey: legacy.trim(),
         baseUrl: defaultBaseUrl('nexusify'),
+        providerName: '',
         modelSelection: 'manual',
         model: 'gpt-4',
+        keepAliveSecs: null,
       }
     }
   } catch {
@@ -104,9 +118,11 @@ export function saveAISettings(settings: AISettings): void {
   const normalized: AISettings = {
     ...settings,
     baseUrl: settings.baseUrl.trim().replace(/\/+$/, '') || defaultBaseUrl(settings.mode),
+    providerName: settings.providerName.trim(),
     model: settings.model.trim(),
     apiKey: settings.apiKey.trim(),
     modelSelection: settings.modelSelection === 'auto_best' ? 'auto_best' : 'manual',
+    keepAliveSecs: normalizeKeepAlive(settings.keepAliveSecs),
   }
   localStorage.setItem(STORAGE_KEY, JSON.stringify(normalized))
 }